@@ -1,50 +1,273 @@
 use std::error::Error;
 
-pub fn solve_a(input: impl Iterator<Item = String>) -> Result<i32, Box<dyn Error>> {
-    let mut score = 0;
-    for line in input {
-        for (str1, str2) in line.split_once(" ") {
-            // println!("{}", str1);
-            // println!("{}", str2);
-            // A: rock, B: paper, C: scissors
-            // X: rock, Y: paper, Z: scissors
-            score += match (str1, str2) {
-                ("A", "X") => 3 + 1,
-                ("A", "Y") => 6 + 2,
-                ("A", "Z") => 0 + 3,
-                ("B", "X") => 0 + 1,
-                ("B", "Y") => 3 + 2,
-                ("B", "Z") => 6 + 3,
-                ("C", "X") => 6 + 1,
-                ("C", "Y") => 0 + 2,
-                ("C", "Z") => 3 + 3,
-                _ => panic!("Unrecognised combination"),
-            };
+use crate::params::Params;
+
+mod analysis;
+mod game;
+
+use self::analysis::Tally;
+use self::game::{Game, Outcome, Shape};
+
+/// A round of the strategy guide, once decoded.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Round {
+    pub theirs: Shape,
+    pub mine: Shape,
+}
+
+/// A way of reading the second column of the strategy guide, given the shape
+/// the opponent is going to throw.
+pub trait Decoder {
+    fn decode(&self, game: &Game, theirs: Shape, symbol: &str) -> Result<Shape, String>;
+}
+
+/// Reads the second column as the shape to throw.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ShapeDecoder {
+    pub symbols: Vec<(String, Shape)>,
+}
+
+impl ShapeDecoder {
+    /// The first interpretation: the last letters of the alphabet, in the
+    /// same order as the shapes, so that X, Y and Z are rock, paper and
+    /// scissors.
+    pub fn part_a(game: &Game) -> Self {
+        let count = game.shapes().count();
+        ShapeDecoder {
+            symbols: game
+                .shapes()
+                .map(|shape| (letter(b'Z' + 1 - count as u8 + shape.0 as u8), shape))
+                .collect(),
+        }
+    }
+
+    /// Lists the symbols and their shapes, e.g. `X=rock, Y=paper, Z=scissors`.
+    pub fn describe(&self, game: &Game) -> String {
+        self.symbols
+            .iter()
+            .map(|(symbol, shape)| format!("{}={}", symbol, game.name(*shape)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Decoder for ShapeDecoder {
+    fn decode(&self, _game: &Game, _theirs: Shape, symbol: &str) -> Result<Shape, String> {
+        self.symbols
+            .iter()
+            .find(|(s, _)| s == symbol)
+            .map(|&(_, shape)| shape)
+            .ok_or_else(|| format!("Unrecognised shape: {:?}", symbol))
+    }
+}
+
+/// Reads the second column as the outcome to aim for.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OutcomeDecoder {
+    pub symbols: Vec<(String, Outcome)>,
+}
+
+impl OutcomeDecoder {
+    /// The second interpretation: X, Y and Z mean lose, draw and win.
+    pub fn part_b() -> Self {
+        let outcomes = [Outcome::Loss, Outcome::Draw, Outcome::Win];
+        OutcomeDecoder {
+            symbols: ["X", "Y", "Z"]
+                .iter()
+                .map(|s| s.to_string())
+                .zip(outcomes)
+                .collect(),
         }
     }
-    Ok(score)
 }
 
-pub fn solve_b(input: impl Iterator<Item = String>) -> Result<i32, Box<dyn Error>> {
-    let mut score = 0;
+impl Decoder for OutcomeDecoder {
+    fn decode(&self, game: &Game, theirs: Shape, symbol: &str) -> Result<Shape, String> {
+        let outcome = self
+            .symbols
+            .iter()
+            .find(|(s, _)| s == symbol)
+            .map(|&(_, outcome)| outcome)
+            .ok_or_else(|| format!("Unrecognised outcome: {:?}", symbol))?;
+        game.shape_for(theirs, outcome)
+            .ok_or_else(|| format!("No shape gets a {} against {}", outcome, game.name(theirs)))
+    }
+}
+
+fn letter(c: u8) -> String {
+    (c as char).to_string()
+}
+
+/// Reads the first column, in which A, B, C and so on are the shapes in order.
+fn decode_theirs(game: &Game, symbol: &str) -> Result<Shape, String> {
+    game.shapes()
+        .find(|shape| letter(b'A' + shape.0 as u8) == symbol)
+        .ok_or_else(|| format!("Unrecognised shape: {:?}", symbol))
+}
 
+pub fn parse(
+    input: impl Iterator<Item = String>,
+    game: &Game,
+    decoder: &impl Decoder,
+) -> Result<Vec<Round>, Box<dyn Error>> {
+    let mut rounds = Vec::new();
     for line in input {
-        for (str1, str2) in line.split_once(" ") {
-            // A: rock, B: paper, C: scissors
-            // X: lose, Y: draw, Z: win
-            score += match (str1, str2) {
-                ("A", "X") => 0 + 3,
-                ("A", "Y") => 3 + 1,
-                ("A", "Z") => 6 + 2,
-                ("B", "X") => 0 + 1,
-                ("B", "Y") => 3 + 2,
-                ("B", "Z") => 6 + 3,
-                ("C", "X") => 0 + 2,
-                ("C", "Y") => 3 + 3,
-                ("C", "Z") => 6 + 1,
-                _ => panic!("Unrecognised combination"),
-            };
+        if line.is_empty() {
+            continue;
         }
+        let (theirs, mine) = line
+            .split_once(' ')
+            .ok_or_else(|| format!("Could not parse line: {:?}", line))?;
+        let theirs = decode_theirs(game, theirs)?;
+        let mine = decoder.decode(game, theirs, mine)?;
+        rounds.push(Round { theirs, mine });
+    }
+    Ok(rounds)
+}
+
+pub fn total_score(game: &Game, rounds: &[Round]) -> i32 {
+    rounds
+        .iter()
+        .map(|round| game.score(round.mine, round.theirs))
+        .sum()
+}
+
+pub fn solve_a(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<i32, Box<dyn Error>> {
+    let game = Game::from_params(params)?;
+    let rounds = parse(input, &game, &ShapeDecoder::part_a(&game))?;
+    Ok(total_score(&game, &rounds))
+}
+
+pub fn solve_b(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<i32, Box<dyn Error>> {
+    let game = Game::from_params(params)?;
+    let rounds = parse(input, &game, &OutcomeDecoder::part_b())?;
+    Ok(total_score(&game, &rounds))
+}
+
+/// Compares both readings of the strategy guide, round by round and in total,
+/// and finds the reading of the second column as shapes that scores the most.
+/// The breakdown of each round can be turned off with the `breakdown` param.
+pub fn solve_guide(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<String, Box<dyn Error>> {
+    let game = Game::from_params(params)?;
+    let breakdown = params.get_or("breakdown", true)?;
+    let lines = input.filter(|line| !line.is_empty()).collect::<Vec<_>>();
+    let shape_decoder = ShapeDecoder::part_a(&game);
+    let as_shapes = parse(lines.iter().cloned(), &game, &shape_decoder)?;
+    let as_outcomes = parse(lines.iter().cloned(), &game, &OutcomeDecoder::part_b())?;
+
+    let mut report = Vec::new();
+    if breakdown {
+        report.push(format!(
+            "{:>5}  {:<5} {:<32} {}",
+            "Round", "Guide", "As shapes", "As outcomes"
+        ));
+        for (idx, line) in lines.iter().enumerate() {
+            report.push(format!(
+                "{:>5}  {:<5} {:<32} {}",
+                idx + 1,
+                line,
+                analysis::describe(&game, &as_shapes[idx]),
+                analysis::describe(&game, &as_outcomes[idx])
+            ));
+        }
+        report.push(String::new());
+    }
+    for (name, rounds) in [("shapes", &as_shapes), ("outcomes", &as_outcomes)] {
+        report.push(format!(
+            "As {}: {} points, {}",
+            name,
+            total_score(&game, rounds),
+            Tally::of(&game, rounds)
+        ));
+    }
+
+    report.push(String::new());
+    report.push("Readings as shapes, best first:".to_string());
+    let decodings = analysis::shape_decodings(&game, &lines)?;
+    for (decoder, score) in &decodings {
+        let marker = if *decoder == shape_decoder {
+            " (puzzle)"
+        } else {
+            ""
+        };
+        report.push(format!(
+            "  {}: {} points{}",
+            decoder.describe(&game),
+            score,
+            marker
+        ));
+    }
+    Ok(report.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "A Y\nB X\nC Z";
+
+    #[test]
+    fn it_solves_the_example() {
+        assert_eq!(
+            solve_a(EXAMPLE.lines().map(String::from), &Params::default()).unwrap(),
+            15
+        );
+        assert_eq!(
+            solve_b(EXAMPLE.lines().map(String::from), &Params::default()).unwrap(),
+            12
+        );
+    }
+
+    #[test]
+    fn it_uses_the_scoring_params() {
+        let params = Params::from_args(&["win=10", "shapes=0,0,0"]);
+        assert_eq!(
+            solve_a(EXAMPLE.lines().map(String::from), &params).unwrap(),
+            13
+        );
+    }
+
+    #[test]
+    fn it_plays_rock_paper_scissors_lizard_spock() {
+        let params = Params::from_args(&["variant=rpsls"]);
+        // Lizard loses to rock, paper beats Spock and Spock loses to lizard
+        let guide = "A Z\nD W\nE Y";
+        assert_eq!(
+            solve_a(guide.lines().map(String::from), &params).unwrap(),
+            5 + 8 + 4
+        );
+        // Lizard is the better of the shapes that lose to rock, and scissors
+        // the better of the shapes that beat lizard
+        let guide = "A X\nD Y\nE Z";
+        assert_eq!(
+            solve_b(guide.lines().map(String::from), &params).unwrap(),
+            5 + 7 + 9
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_guides() {
+        assert!(solve_a("A Y\nD X".lines().map(String::from), &Params::default()).is_err());
+        assert!(solve_a("A W".lines().map(String::from), &Params::default()).is_err());
+        assert!(solve_b("AY".lines().map(String::from), &Params::default()).is_err());
+        assert!(solve_a(
+            EXAMPLE.lines().map(String::from),
+            &Params::from_args(&["variant=chess"])
+        )
+        .is_err());
+        assert!(solve_a(
+            EXAMPLE.lines().map(String::from),
+            &Params::from_args(&["shapes=1,2"])
+        )
+        .is_err());
     }
-    Ok(score)
 }
@@ -0,0 +1,109 @@
+use std::{error::Error, fmt};
+
+use itertools::Itertools;
+
+use super::game::{Game, Outcome};
+use super::{parse, total_score, Round, ShapeDecoder};
+
+/// How many rounds were won, drawn and lost.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Tally {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Tally {
+    pub fn of(game: &Game, rounds: &[Round]) -> Self {
+        let mut tally = Tally::default();
+        for round in rounds {
+            match game.outcome(round.mine, round.theirs) {
+                Outcome::Win => tally.wins += 1,
+                Outcome::Draw => tally.draws += 1,
+                Outcome::Loss => tally.losses += 1,
+            }
+        }
+        tally
+    }
+}
+
+impl fmt::Display for Tally {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} won, {} drawn, {} lost",
+            self.wins, self.draws, self.losses
+        )
+    }
+}
+
+/// Describes a round from our side, e.g. `paper v rock: win (8)`.
+pub fn describe(game: &Game, round: &Round) -> String {
+    format!(
+        "{} v {}: {} ({})",
+        game.name(round.mine),
+        game.name(round.theirs),
+        game.outcome(round.mine, round.theirs),
+        game.score(round.mine, round.theirs)
+    )
+}
+
+/// Every way of reading the second column as shapes, along with the total
+/// score each gives, from highest to lowest. Decodings with the same score are
+/// kept in the order of the permutations, so the puzzle's own decoding comes
+/// first among them.
+pub fn shape_decodings(
+    game: &Game,
+    lines: &[String],
+) -> Result<Vec<(ShapeDecoder, i32)>, Box<dyn Error>> {
+    let symbols = ShapeDecoder::part_a(game)
+        .symbols
+        .into_iter()
+        .map(|(symbol, _)| symbol)
+        .collect::<Vec<_>>();
+    let mut decodings = Vec::new();
+    for shapes in game.shapes().permutations(symbols.len()) {
+        let decoder = ShapeDecoder {
+            symbols: symbols.iter().cloned().zip(shapes).collect(),
+        };
+        let rounds = parse(lines.iter().cloned(), game, &decoder)?;
+        let score = total_score(game, &rounds);
+        decodings.push((decoder, score));
+    }
+    decodings.sort_by_key(|&(_, score)| -score);
+    Ok(decodings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_finds_the_best_shape_decoding() {
+        let game = Game::rock_paper_scissors();
+        let lines = ["A Y", "B X", "C Z"].map(|x| x.to_string());
+        let decodings = shape_decodings(&game, &lines).unwrap();
+        assert_eq!(decodings.len(), 6);
+        // Reading X, Y and Z as scissors, paper and rock wins every round
+        assert_eq!(
+            decodings[0].0.describe(&game),
+            "X=scissors, Y=paper, Z=rock"
+        );
+        assert_eq!(decodings[0].1, 24);
+        let scores = decodings
+            .iter()
+            .map(|(_, score)| *score)
+            .collect::<Vec<_>>();
+        assert_eq!(scores, vec![24, 15, 15, 15, 15, 6]);
+    }
+
+    #[test]
+    fn it_tallies_outcomes() {
+        let game = Game::rock_paper_scissors();
+        let lines = ["A Y", "B X", "C Z", "A Y"].map(|x| x.to_string());
+        let rounds = parse(lines.into_iter(), &game, &ShapeDecoder::part_a(&game)).unwrap();
+        let tally = Tally::of(&game, &rounds);
+        assert_eq!(tally.to_string(), "2 won, 1 drawn, 1 lost");
+        assert_eq!(describe(&game, &rounds[1]), "rock v paper: loss (1)");
+    }
+}
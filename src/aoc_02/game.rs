@@ -0,0 +1,210 @@
+use std::{error::Error, fmt};
+
+use crate::params::Params;
+
+/// A shape that can be thrown, identified by its position in the game's list
+/// of shapes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Shape(pub usize);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Outcome {
+    Loss,
+    Draw,
+    Win,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Outcome::Loss => "loss",
+            Outcome::Draw => "draw",
+            Outcome::Win => "win",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// The points scored for the shape thrown, and for the outcome of the round.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Scoring {
+    pub shapes: Vec<i32>,
+    pub loss: i32,
+    pub draw: i32,
+    pub win: i32,
+}
+
+impl Scoring {
+    /// The scoring from the puzzle: one point for the first shape, two for the
+    /// second and so on, with 0, 3 and 6 points for a loss, draw and win.
+    pub fn standard(shape_count: usize) -> Self {
+        Scoring {
+            shapes: (1..=shape_count as i32).collect(),
+            loss: 0,
+            draw: 3,
+            win: 6,
+        }
+    }
+
+    /// Overrides the defaults with any of the params `loss`, `draw`, `win` and
+    /// `shapes` (comma-separated).
+    pub fn with_params(self, params: &Params) -> Result<Self, Box<dyn Error>> {
+        Ok(Scoring {
+            shapes: params.get_list_or("shapes", self.shapes)?,
+            loss: params.get_or("loss", self.loss)?,
+            draw: params.get_or("draw", self.draw)?,
+            win: params.get_or("win", self.win)?,
+        })
+    }
+
+    pub fn outcome(&self, outcome: Outcome) -> i32 {
+        match outcome {
+            Outcome::Loss => self.loss,
+            Outcome::Draw => self.draw,
+            Outcome::Win => self.win,
+        }
+    }
+}
+
+/// A game in the style of rock-paper-scissors, where every pair of different
+/// shapes has a winner.
+#[derive(Debug, Clone)]
+pub struct Game {
+    pub names: Vec<String>,
+    /// `beats[a][b]` is whether shape `a` beats shape `b`.
+    beats: Vec<Vec<bool>>,
+    pub scoring: Scoring,
+}
+
+impl Game {
+    /// A game with an odd number of shapes, in which each shape beats the
+    /// shapes an odd number of places before it, wrapping around. With three
+    /// shapes, each shape beats the one before it.
+    pub fn cyclic(names: &[&str]) -> Result<Self, String> {
+        let n = names.len();
+        if n.is_multiple_of(2) {
+            return Err(format!(
+                "A cyclic game needs an odd number of shapes, not {}",
+                n
+            ));
+        }
+        let beats = (0..n)
+            .map(|a| (0..n).map(|b| (a + n - b) % n % 2 == 1).collect())
+            .collect();
+        Ok(Game {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            beats,
+            scoring: Scoring::standard(n),
+        })
+    }
+
+    pub fn rock_paper_scissors() -> Self {
+        Game::cyclic(&["rock", "paper", "scissors"]).unwrap()
+    }
+
+    /// Rock-paper-scissors-lizard-Spock, in which scissors cuts paper, paper
+    /// covers rock, rock crushes lizard, lizard poisons Spock, Spock smashes
+    /// scissors, scissors decapitates lizard, lizard eats paper, paper
+    /// disproves Spock, Spock vaporizes rock, and rock crushes scissors.
+    pub fn rock_paper_scissors_lizard_spock() -> Self {
+        Game::cyclic(&["rock", "paper", "scissors", "Spock", "lizard"]).unwrap()
+    }
+
+    /// Picks the variant given by the `variant` param, with its scoring
+    /// overridden by any other params.
+    pub fn from_params(params: &Params) -> Result<Self, Box<dyn Error>> {
+        let mut game = match params.get("variant").unwrap_or("rps") {
+            "rps" => Game::rock_paper_scissors(),
+            "rpsls" => Game::rock_paper_scissors_lizard_spock(),
+            variant => return Err(format!("Unknown variant: {}", variant).into()),
+        };
+        game.scoring = game.scoring.with_params(params)?;
+        if game.scoring.shapes.len() != game.shapes().count() {
+            return Err("There should be a score for each shape".into());
+        }
+        Ok(game)
+    }
+
+    pub fn shapes(&self) -> impl Iterator<Item = Shape> {
+        (0..self.names.len()).map(Shape)
+    }
+
+    pub fn name(&self, shape: Shape) -> &str {
+        &self.names[shape.0]
+    }
+
+    pub fn beats(&self, a: Shape, b: Shape) -> bool {
+        self.beats[a.0][b.0]
+    }
+
+    /// The outcome for the player throwing `mine`.
+    pub fn outcome(&self, mine: Shape, theirs: Shape) -> Outcome {
+        if self.beats(mine, theirs) {
+            Outcome::Win
+        } else if self.beats(theirs, mine) {
+            Outcome::Loss
+        } else {
+            Outcome::Draw
+        }
+    }
+
+    pub fn score(&self, mine: Shape, theirs: Shape) -> i32 {
+        self.scoring.shapes[mine.0] + self.scoring.outcome(self.outcome(mine, theirs))
+    }
+
+    /// The highest-scoring shape to throw to get the outcome.
+    pub fn shape_for(&self, theirs: Shape, outcome: Outcome) -> Option<Shape> {
+        self.shapes()
+            .filter(|&mine| self.outcome(mine, theirs) == outcome)
+            .max_by_key(|&mine| self.score(mine, theirs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_plays_rock_paper_scissors() {
+        let game = Game::rock_paper_scissors();
+        let (rock, paper, scissors) = (Shape(0), Shape(1), Shape(2));
+        assert_eq!(game.outcome(paper, rock), Outcome::Win);
+        assert_eq!(game.outcome(rock, paper), Outcome::Loss);
+        assert_eq!(game.outcome(rock, scissors), Outcome::Win);
+        assert_eq!(game.outcome(scissors, scissors), Outcome::Draw);
+        assert_eq!(game.score(paper, rock), 8);
+        assert_eq!(game.shape_for(rock, Outcome::Loss), Some(scissors));
+    }
+
+    #[test]
+    fn it_plays_rock_paper_scissors_lizard_spock() {
+        let game = Game::rock_paper_scissors_lizard_spock();
+        let shape = |name| game.shapes().find(|&s| game.name(s) == name).unwrap();
+        let wins = [
+            ("scissors", "paper"),
+            ("paper", "rock"),
+            ("rock", "lizard"),
+            ("lizard", "Spock"),
+            ("Spock", "scissors"),
+            ("scissors", "lizard"),
+            ("lizard", "paper"),
+            ("paper", "Spock"),
+            ("Spock", "rock"),
+            ("rock", "scissors"),
+        ];
+        for (a, b) in wins {
+            assert_eq!(game.outcome(shape(a), shape(b)), Outcome::Win);
+            assert_eq!(game.outcome(shape(b), shape(a)), Outcome::Loss);
+        }
+        // Both Spock and paper beat rock, and Spock scores more
+        assert_eq!(
+            game.shape_for(shape("rock"), Outcome::Win),
+            Some(shape("Spock"))
+        );
+    }
+
+    #[test]
+    fn it_rejects_games_without_a_winner() {
+        assert!(Game::cyclic(&["rock", "paper"]).is_err());
+    }
+}
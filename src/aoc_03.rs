@@ -1,59 +1,113 @@
+use std::error::Error;
+
 use itertools::Itertools;
-use std::collections::HashSet;
-
-fn get_score_char(char: &char) -> u32 {
-    // println!("{}", char);
-    if char.is_ascii_uppercase() {
-        char.to_digit(36).unwrap() + 17
-    } else {
-        char.to_digit(36).unwrap() - 9
+
+use crate::params::Params;
+
+mod item_set;
+
+use self::item_set::ItemSet;
+
+/// The priority of the only item type in the set, or an error describing what
+/// was found instead.
+fn only_priority(set: ItemSet, what: &str) -> Result<u32, String> {
+    match set.len() {
+        1 => Ok(set.priority_sum()),
+        0 => Err(format!("{} has no common item", what)),
+        _ => Err(format!("{} has several common items: {}", what, set)),
     }
 }
 
-pub fn solve_a(input: impl Iterator<Item = String>) -> Option<u32> {
+pub fn solve_a(input: impl Iterator<Item = String>) -> Result<u32, Box<dyn Error>> {
     let mut count = 0;
 
-    for line in input {
-        let length = line.chars().count();
-        let (str1, str2) = line.split_at(length / 2);
+    for (idx, line) in input.enumerate() {
+        if !line.len().is_multiple_of(2) {
+            return Err(format!("Rucksack {} has an odd number of items", idx + 1).into());
+        }
+        let (str1, str2) = line
+            .split_at_checked(line.len() / 2)
+            .ok_or_else(|| format!("Rucksack {} has unrecognised items", idx + 1))?;
+        let common = str1.parse::<ItemSet>()? & str2.parse::<ItemSet>()?;
+        count += only_priority(common, &format!("Rucksack {}", idx + 1))?;
+    }
+    Ok(count)
+}
 
-        let mut hash_set1 = HashSet::new();
-        let mut hash_set2 = HashSet::new();
+/// Finds the badge shared by each group of elves, with groups of three unless
+/// set by the `group` param.
+pub fn solve_b(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<u32, Box<dyn Error>> {
+    let group_size: usize = params.get_or("group", 3)?;
+    if group_size == 0 {
+        return Err("Groups must have at least one elf".into());
+    }
+    let mut count = 0;
 
-        for c in str1.chars() {
-            hash_set1.insert(c);
-        }
-        for c in str2.chars() {
-            hash_set2.insert(c);
+    for (idx, lines) in (&input.chunks(group_size)).into_iter().enumerate() {
+        let rucksacks = lines
+            .map(|line| line.parse::<ItemSet>())
+            .collect::<Result<Vec<_>, _>>()?;
+        if rucksacks.len() != group_size {
+            return Err(format!("Group {} has only {} elves", idx + 1, rucksacks.len()).into());
         }
-        let intersect = hash_set1.intersection(&hash_set2).next().unwrap();
-        // println!("{} has score: {}", intersect, get_score(intersect));
-        count += get_score_char(intersect);
+        let common = rucksacks.into_iter().fold(ItemSet::all(), |a, b| a & b);
+        count += only_priority(common, &format!("Group {}", idx + 1))?;
     }
-    Some(count)
+    Ok(count)
 }
 
-pub fn solve_b(input: impl Iterator<Item = String>) -> Option<u32> {
-    let mut count = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    for mut lines in &input.chunks(3) {
-        let mut hash_set1 = HashSet::new();
-        let mut hash_set2 = HashSet::new();
-        let mut hash_set3 = HashSet::new();
+    const EXAMPLE: &str = "vJrwpWtwJgWrhcsFMMfFFhFp
+jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
+PmmdzqPrVvPwwTWBwg
+wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
+ttgJtRGJQctTZtZT
+CrZsJsPPZsGzwwsLwLmpwMDw";
 
-        for c in lines.next().unwrap().chars() {
-            hash_set1.insert(c);
-        }
-        for c in lines.next().unwrap().chars() {
-            hash_set2.insert(c);
-        }
-        for c in lines.next().unwrap().chars() {
-            hash_set3.insert(c);
-        }
+    #[test]
+    fn it_solves_the_example() {
+        assert_eq!(solve_a(EXAMPLE.lines().map(String::from)).unwrap(), 157);
+        assert_eq!(
+            solve_b(EXAMPLE.lines().map(String::from), &Params::default()).unwrap(),
+            70
+        );
+    }
+
+    #[test]
+    fn it_supports_other_group_sizes() {
+        let params = Params::from_args(&["group=1"]);
+        let result = solve_b("aa\nbb\ncc".lines().map(String::from), &params);
+        assert_eq!(result.unwrap(), 6);
+        let params = Params::from_args(&["group=2"]);
+        let result = solve_b("ab\nbc\ncd\nde".lines().map(String::from), &params);
+        assert_eq!(result.unwrap(), 2 + 4);
+        let params = Params::from_args(&["group=6"]);
+        let result = solve_b(EXAMPLE.lines().map(String::from), &params);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Group 1 has no common item"
+        );
+    }
 
-        let int1: HashSet<char> = hash_set1.intersection(&hash_set2).copied().collect();
-        let mut int2 = int1.intersection(&hash_set3);
-        count += get_score_char(&int2.next().unwrap());
+    #[test]
+    fn it_rejects_groups_without_a_single_badge() {
+        let params = Params::default();
+        let result = solve_b("abc\nabd\nabe".lines().map(String::from), &params);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Group 1 has several common items: ab"
+        );
+        assert!(solve_b("ab\nab".lines().map(String::from), &params).is_err());
+        let params = Params::from_args(&["group=0"]);
+        assert!(solve_b(EXAMPLE.lines().map(String::from), &params).is_err());
+        assert!(solve_a("abc".lines().map(String::from)).is_err());
+        assert!(solve_a("abcd".lines().map(String::from)).is_err());
+        assert!(solve_a("aéa".lines().map(String::from)).is_err());
     }
-    Some(count)
 }
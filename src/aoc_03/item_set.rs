@@ -0,0 +1,109 @@
+use std::{
+    fmt,
+    ops::{BitAnd, BitOr},
+    str::FromStr,
+};
+
+/// The priority of an item type: a to z have priorities 1 to 26, and A to Z
+/// have priorities 27 to 52.
+pub fn priority(item: char) -> Option<u32> {
+    match item {
+        'a'..='z' => Some(item as u32 - 'a' as u32 + 1),
+        'A'..='Z' => Some(item as u32 - 'A' as u32 + 27),
+        _ => None,
+    }
+}
+
+fn item(priority: u32) -> char {
+    match priority {
+        1..=26 => (b'a' + (priority - 1) as u8) as char,
+        _ => (b'A' + (priority - 27) as u8) as char,
+    }
+}
+
+/// A set of item types, with bit `p` set for the item type of priority `p`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ItemSet(u64);
+
+impl ItemSet {
+    /// Every item type.
+    pub fn all() -> Self {
+        ItemSet(((1 << 52) - 1) << 1)
+    }
+
+    pub fn len(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// The priorities of the item types in the set, from lowest to highest.
+    pub fn priorities(&self) -> impl Iterator<Item = u32> + '_ {
+        (1..=52).filter(|p| self.0 & (1 << p) != 0)
+    }
+
+    pub fn priority_sum(&self) -> u32 {
+        self.priorities().sum()
+    }
+}
+
+impl FromStr for ItemSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut set = ItemSet::default();
+        for c in s.chars() {
+            let p = priority(c).ok_or_else(|| format!("Unrecognised item: {:?}", c))?;
+            set.0 |= 1 << p;
+        }
+        Ok(set)
+    }
+}
+
+impl fmt::Display for ItemSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.priorities().map(item).collect::<String>())
+    }
+}
+
+impl BitAnd for ItemSet {
+    type Output = ItemSet;
+
+    fn bitand(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
+    }
+}
+
+impl BitOr for ItemSet {
+    type Output = ItemSet;
+
+    fn bitor(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 | other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_scores_priorities() {
+        assert_eq!(priority('a'), Some(1));
+        assert_eq!(priority('z'), Some(26));
+        assert_eq!(priority('A'), Some(27));
+        assert_eq!(priority('Z'), Some(52));
+        assert_eq!(priority('1'), None);
+        assert_eq!(ItemSet::all().len(), 52);
+        assert_eq!(ItemSet::all().priority_sum(), 52 * 53 / 2);
+    }
+
+    #[test]
+    fn it_combines_sets() {
+        let a: ItemSet = "vJrwpWtwJgWr".parse().unwrap();
+        let b: ItemSet = "hcsFMMfFFhFp".parse().unwrap();
+        assert_eq!((a & b).to_string(), "p");
+        assert_eq!((a & b).priority_sum(), 16);
+        assert_eq!((a | b).to_string(), "cfghprstvwFJMW");
+        assert_eq!((a & "xyz".parse().unwrap()).len(), 0);
+        assert_eq!(ItemSet::all() & a, a);
+        assert!("ab-c".parse::<ItemSet>().is_err());
+    }
+}
@@ -1,4 +1,7 @@
 use std::error::Error;
+use std::ops::Range;
+
+use crate::intervals::IntervalSet;
 
 fn parse_pairs<T: Iterator<Item = String>>(
     input: T,
@@ -17,10 +20,18 @@ fn parse_pairs<T: Iterator<Item = String>>(
     })
 }
 
+/// The sections assigned to an elf, as a half-open range.
+fn sections((start, end): (i32, i32)) -> Range<i64> {
+    start as i64..end as i64 + 1
+}
+
 pub fn solve_a(input: impl Iterator<Item = String>) -> Result<i32, Box<dyn Error>> {
     let mut count = 0;
-    for ((a_start, a_end), (b_start, b_end)) in parse_pairs(input) {
-        if (a_start <= b_start && b_end <= a_end) || (b_start <= a_start && a_end <= b_end) {
+    for (a, b) in parse_pairs(input) {
+        let (a_sections, b_sections) = (sections(a), sections(b));
+        if IntervalSet::from(a_sections.clone()).contains_range(b_sections.clone())
+            || IntervalSet::from(b_sections).contains_range(a_sections)
+        {
             count += 1;
         }
     }
@@ -29,8 +40,8 @@ pub fn solve_a(input: impl Iterator<Item = String>) -> Result<i32, Box<dyn Error
 
 pub fn solve_b(input: impl Iterator<Item = String>) -> Result<i32, Box<dyn Error>> {
     let mut count = 0;
-    for ((a_start, a_end), (b_start, b_end)) in parse_pairs(input) {
-        if !((a_end < b_start) || (b_end < a_start)) {
+    for (a, b) in parse_pairs(input) {
+        if IntervalSet::from(sections(a)).overlaps(sections(b)) {
             count += 1;
         }
     }
@@ -1,113 +1,107 @@
-use regex::Regex;
 use std::error::Error;
 
-fn parse_crates(lines: &Vec<String>) -> Vec<Vec<char>> {
-    let re = Regex::new(r"(?:\[(.)\]|(    ))").unwrap();
+use crate::params::Params;
 
-    let mut crates: Vec<Vec<char>> = Vec::new();
-    for _ in 0..9 {
-        crates.push(Vec::new());
-    }
+mod stacks;
 
-    for line in lines {
-        for (idx, cap) in re.captures_iter(&line).enumerate() {
-            if let Some(char_str) = cap.get(1) {
-                let c = char_str.as_str().chars().next().expect("Must be a char");
-                crates[idx].insert(0, c)
-            }
-        }
-    }
-    crates
-}
+use self::stacks::{Crane, Instruction, Stacks};
 
-#[derive(Debug)]
-struct Instruction {
-    count: usize,
-    origin: usize,
-    destination: usize,
+/// Splits the input into the drawing of the stacks and the instructions that
+/// follow it after a blank line.
+fn parse(
+    input: impl Iterator<Item = String>,
+) -> Result<(Stacks, Vec<Instruction>), Box<dyn Error>> {
+    let lines: Vec<String> = input.collect();
+    let blank = lines
+        .iter()
+        .position(|line| line.is_empty())
+        .unwrap_or(lines.len());
+    let stacks = Stacks::parse(&lines[..blank])?;
+    let instructions = lines[blank..]
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.parse())
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((stacks, instructions))
 }
 
-impl Instruction {
-    fn parse(line: &str) -> Option<Instruction> {
-        let re = Regex::new(r"move (\d+) from (\d+) to (\d+)").unwrap();
-        let regex_match = re.captures(line)?;
-
-        Some(Instruction {
-            count: regex_match
-                .get(1)?
-                .as_str()
-                .parse()
-                .expect("Could not parse integer"),
-            origin: regex_match
-                .get(2)?
-                .as_str()
-                .parse()
-                .expect("Could not parse integer"),
-            destination: regex_match
-                .get(3)?
-                .as_str()
-                .parse()
-                .expect("Could not parse integer"),
-        })
+fn run(input: impl Iterator<Item = String>, crane: Crane) -> Result<String, Box<dyn Error>> {
+    let (mut stacks, instructions) = parse(input)?;
+    for instruction in &instructions {
+        stacks.apply(instruction, crane)?;
     }
+    Ok(stacks.tops())
 }
 
-fn parse_instructions(lines: &Vec<String>) -> Vec<Instruction> {
-    let mut instructions: Vec<Instruction> = Vec::new();
-    for line in lines {
-        let maybe_instruction = Instruction::parse(line);
-        if let Some(instruction) = maybe_instruction {
-            instructions.push(instruction);
-        }
-    }
-    instructions
+pub fn solve_a(input: impl Iterator<Item = String>) -> Result<String, Box<dyn Error>> {
+    run(input, Crane::CrateMover9000)
 }
 
-fn get_last_crates(crates: &Vec<Vec<char>>) -> String {
-    let last_crates: String = crates
-        .iter()
-        .map(|crate_pile| {
-            crate_pile
-                .last()
-                .or(Some(&' '))
-                .expect("Not a char")
-                .to_string()
-        })
-        .reduce(|acc, item| format!("{}{}", acc, item))
-        .expect("Empty array");
-    last_crates
+pub fn solve_b(input: impl Iterator<Item = String>) -> Result<String, Box<dyn Error>> {
+    run(input, Crane::CrateMover9001)
 }
 
-pub fn solve_a(input: impl Iterator<Item = String>) -> Result<String, Box<dyn Error>> {
-    let lines: Vec<String> = input.collect();
-    let mut crates = parse_crates(&lines);
-    let instructions = parse_instructions(&lines);
-
-    for instruction in instructions {
-        for _ in 0..instruction.count {
-            let moved_crate = crates[instruction.origin - 1]
-                .pop()
-                .expect("Attempted to move from empty stack");
-            crates[instruction.destination - 1].push(moved_crate);
-        }
+/// Draws the stacks before the first move and after each move, using the
+/// crane given by the `crane` param (9000 by default).
+pub fn solve_trace(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<String, Box<dyn Error>> {
+    let crane = params.get_or("crane", Crane::CrateMover9000)?;
+    let (mut stacks, instructions) = parse(input)?;
+    let mut drawings = vec![stacks.to_string()];
+    for instruction in &instructions {
+        stacks.apply(instruction, crane)?;
+        drawings.push(format!("{}\n\n{}", instruction, stacks));
     }
-
-    Ok(get_last_crates(&crates))
+    Ok(drawings.join("\n\n"))
 }
 
-pub fn solve_b(input: impl Iterator<Item = String>) -> Result<String, Box<dyn Error>> {
-    let lines: Vec<String> = input.collect();
-    let mut crates = parse_crates(&lines);
-    let instructions = parse_instructions(&lines);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    for instruction in instructions {
-        let origin_stack = &crates[instruction.origin - 1];
-        let mut idx: usize = origin_stack.len().into();
-        idx -= instruction.count;
-        let moved_crates: Vec<char> = Vec::from(&origin_stack[idx..]);
-        crates[instruction.destination - 1].extend(moved_crates);
-        crates[instruction.origin - 1].truncate(idx);
+    const EXAMPLE: &str = "    [D]
+[N] [C]
+[Z] [M] [P]
+ 1   2   3
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2";
+
+    #[test]
+    fn it_solves_the_example() {
+        assert_eq!(solve_a(EXAMPLE.lines().map(String::from)).unwrap(), "CMZ");
+        assert_eq!(solve_b(EXAMPLE.lines().map(String::from)).unwrap(), "MCD");
     }
 
-    Ok(get_last_crates(&crates))
+    #[test]
+    fn it_draws_each_move() {
+        let trace = solve_trace(EXAMPLE.lines().map(String::from), &Params::default()).unwrap();
+        let drawings = trace.split("\n\n").collect::<Vec<_>>();
+        assert_eq!(drawings.len(), 9);
+        assert_eq!(drawings[0], EXAMPLE.split("\n\n").next().unwrap());
+        assert_eq!(drawings[1], "move 1 from 2 to 1");
+        assert_eq!(drawings[2], "[D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3");
+        assert_eq!(
+            drawings[4],
+            "        [Z]\n        [N]\n    [C] [D]\n    [M] [P]\n 1   2   3"
+        );
+    }
+
+    #[test]
+    fn it_rejects_impossible_moves() {
+        let input = EXAMPLE.replace("move 3 from 1", "move 4 from 1");
+        let error = solve_a(input.lines().map(String::from)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "move 4 from 1 to 3: stack 1 only has 3 crates"
+        );
+        let input = EXAMPLE.replace("to 1", "to 4");
+        assert!(solve_b(input.lines().map(String::from)).is_err());
+        let input = EXAMPLE.replace("move 1", "mvoe 1");
+        assert!(solve_a(input.lines().map(String::from)).is_err());
+    }
 }
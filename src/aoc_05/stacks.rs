@@ -0,0 +1,228 @@
+use std::{fmt, str::FromStr};
+
+use regex::Regex;
+
+/// A move of some crates from one stack to another. Stacks are numbered from
+/// one, as in the puzzle.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Instruction {
+    pub count: usize,
+    pub origin: usize,
+    pub destination: usize,
+}
+
+impl FromStr for Instruction {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        lazy_static! {
+            static ref RE: Regex = Regex::new(r"^move (\d+) from (\d+) to (\d+)$").unwrap();
+        }
+        let error = || format!("Could not parse instruction: {:?}", line);
+        let captures = RE.captures(line).ok_or_else(error)?;
+        let number = |idx: usize| captures[idx].parse::<usize>().map_err(|_| error());
+        Ok(Instruction {
+            count: number(1)?,
+            origin: number(2)?,
+            destination: number(3)?,
+        })
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.count, self.origin, self.destination
+        )
+    }
+}
+
+/// Which crane is doing the moving.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Crane {
+    /// Moves crates one at a time, reversing their order.
+    CrateMover9000,
+    /// Moves several crates at once, keeping their order.
+    CrateMover9001,
+}
+
+impl FromStr for Crane {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "9000" => Ok(Crane::CrateMover9000),
+            "9001" => Ok(Crane::CrateMover9001),
+            _ => Err(format!("Unknown crane: CrateMover {}", s)),
+        }
+    }
+}
+
+/// The stacks of crates, each from the bottom up.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Stacks {
+    stacks: Vec<Vec<char>>,
+}
+
+impl Stacks {
+    /// Parses the drawing of the stacks, whose last line numbers each stack.
+    pub fn parse(drawing: &[String]) -> Result<Stacks, String> {
+        let (footer, rows) = drawing.split_last().ok_or("There is no drawing")?;
+        let numbers = footer.split_whitespace().collect::<Vec<_>>();
+        for (idx, number) in numbers.iter().enumerate() {
+            if *number != (idx + 1).to_string() {
+                return Err(format!("Expected stack {}, found {:?}", idx + 1, number));
+            }
+        }
+        if numbers.is_empty() {
+            return Err("There are no stacks".to_string());
+        }
+
+        let mut stacks = vec![Vec::new(); numbers.len()];
+        for (row_idx, row) in rows.iter().enumerate().rev() {
+            let cells = row.chars().collect::<Vec<_>>();
+            for (idx, cell) in cells.chunks(4).enumerate() {
+                let item = match cell {
+                    ['[', c, ']'] | ['[', c, ']', ' '] => *c,
+                    _ if cell.iter().all(|&c| c == ' ') => continue,
+                    _ => return Err(format!("Could not parse row: {:?}", row)),
+                };
+                if idx >= stacks.len() {
+                    return Err(format!("Crate {} is not above a numbered stack", item));
+                }
+                // The rows are read from the bottom up, so every crate below
+                // this one has already been stacked
+                if stacks[idx].len() != rows.len() - 1 - row_idx {
+                    return Err(format!("Crate {} is not resting on anything", item));
+                }
+                stacks[idx].push(item);
+            }
+        }
+        Ok(Stacks { stacks })
+    }
+
+    /// Carries out a move, checking that the stacks exist and that there are
+    /// enough crates to move.
+    pub fn apply(&mut self, instruction: &Instruction, crane: Crane) -> Result<(), String> {
+        for stack in [instruction.origin, instruction.destination] {
+            if stack == 0 || stack > self.stacks.len() {
+                return Err(format!("{}: there is no stack {}", instruction, stack));
+            }
+        }
+        let origin = &mut self.stacks[instruction.origin - 1];
+        let height = origin.len().checked_sub(instruction.count).ok_or_else(|| {
+            format!(
+                "{}: stack {} only has {} crates",
+                instruction,
+                instruction.origin,
+                origin.len()
+            )
+        })?;
+        // Either crane puts crates moved within a stack back as they were
+        if instruction.origin == instruction.destination {
+            return Ok(());
+        }
+        let mut moved = origin.split_off(height);
+        if crane == Crane::CrateMover9000 {
+            moved.reverse();
+        }
+        self.stacks[instruction.destination - 1].extend(moved);
+        Ok(())
+    }
+
+    /// The crate on top of each stack, with a space for an empty stack.
+    pub fn tops(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|stack| stack.last().unwrap_or(&' '))
+            .collect()
+    }
+}
+
+impl fmt::Display for Stacks {
+    /// Draws the stacks as in the puzzle.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let height = self
+            .stacks
+            .iter()
+            .map(|stack| stack.len())
+            .max()
+            .unwrap_or(0);
+        for level in (0..height).rev() {
+            let cells = self.stacks.iter().map(|stack| match stack.get(level) {
+                Some(c) => format!("[{}]", c),
+                None => "   ".to_string(),
+            });
+            writeln!(f, "{}", cells.collect::<Vec<_>>().join(" ").trim_end())?;
+        }
+        let numbers = (1..=self.stacks.len()).map(|n| format!("{:^3}", n));
+        write!(f, "{}", numbers.collect::<Vec<_>>().join(" ").trim_end())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drawing(s: &str) -> Vec<String> {
+        s.lines().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn it_infers_the_number_of_stacks() {
+        let stacks = Stacks::parse(&drawing("[A]\n 1")).unwrap();
+        assert_eq!(stacks.tops(), "A");
+        let stacks = Stacks::parse(&drawing(
+            "                                        [K]\n 1   2   3   4   5   6   7   8   9  10  11",
+        ))
+        .unwrap();
+        assert_eq!(stacks.tops(), "          K");
+        assert!(Stacks::parse(&drawing("[A] [B]\n 1")).is_err());
+        assert!(Stacks::parse(&drawing("[A]\n 1   3")).is_err());
+    }
+
+    #[test]
+    fn it_rejects_invalid_drawings() {
+        assert!(Stacks::parse(&drawing("[A]\n    [B]\n 1   2")).is_err());
+        assert!(Stacks::parse(&drawing("[A}\n 1")).is_err());
+        assert!(Stacks::parse(&drawing("")).is_err());
+    }
+
+    #[test]
+    fn it_validates_moves() {
+        let mut stacks = Stacks::parse(&drawing("[A]\n[B] [C]\n 1   2")).unwrap();
+        let instruction = |s: &str| s.parse::<Instruction>().unwrap();
+        let error = stacks
+            .apply(&instruction("move 3 from 1 to 2"), Crane::CrateMover9000)
+            .unwrap_err();
+        assert_eq!(error, "move 3 from 1 to 2: stack 1 only has 2 crates");
+        assert!(stacks
+            .apply(&instruction("move 1 from 3 to 2"), Crane::CrateMover9000)
+            .is_err());
+        assert!(stacks
+            .apply(&instruction("move 1 from 1 to 0"), Crane::CrateMover9000)
+            .is_err());
+        assert!("move 1 from 1".parse::<Instruction>().is_err());
+        // Failed moves leave the stacks as they were
+        assert_eq!(stacks.tops(), "AC");
+    }
+
+    #[test]
+    fn it_moves_crates_within_a_stack() {
+        let mut stacks = Stacks::parse(&drawing(
+            "[A]
+[B] [C]
+ 1   2",
+        ))
+        .unwrap();
+        let instruction = "move 2 from 1 to 1".parse::<Instruction>().unwrap();
+        for crane in [Crane::CrateMover9000, Crane::CrateMover9001] {
+            stacks.apply(&instruction, crane).unwrap();
+            assert_eq!(stacks.to_string(), "[A]\n[B] [C]\n 1   2");
+        }
+        let instruction = "move 3 from 1 to 1".parse::<Instruction>().unwrap();
+        assert!(stacks.apply(&instruction, Crane::CrateMover9000).is_err());
+    }
+}
@@ -1,22 +1,113 @@
-use std::collections::HashSet;
-
-fn find_window(line: &str, length: usize) -> Option<usize> {
-    let chars: Vec<char> = line.chars().collect();
-    for i in 0..(chars.len() - length) {
-        let uniq_chars: HashSet<&char> = (&chars[i..i + length]).iter().collect();
-        if uniq_chars.len() == length {
-            return Some(i + length);
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufReader, Read},
+};
+
+use itertools::Itertools;
+
+use crate::params::Params;
+
+mod marker;
+
+use self::marker::{markers, MarkerDetector};
+
+/// The bytes of the input, with the line breaks put back.
+fn bytes(input: impl Iterator<Item = String>) -> impl Iterator<Item = u8> {
+    input.enumerate().flat_map(|(idx, line)| {
+        let line_break = (idx > 0).then_some(b'\n');
+        line_break.into_iter().chain(line.into_bytes())
+    })
+}
+
+fn find_marker(
+    input: impl Iterator<Item = String>,
+    length: usize,
+) -> Result<usize, Box<dyn Error>> {
+    let first = markers(bytes(input), length)?.next();
+    Ok(first.ok_or("Could not find char group")?)
+}
+
+pub fn solve_a(input: impl Iterator<Item = String>) -> Result<usize, Box<dyn Error>> {
+    find_marker(input, 4)
+}
+
+pub fn solve_b(input: impl Iterator<Item = String>) -> Result<usize, Box<dyn Error>> {
+    find_marker(input, 14)
+}
+
+/// Lists the end position of every marker of the given `length` (4 by
+/// default), or just the first if `all=false`. The stream is read from the
+/// file given by the `source` param, or from stdin if it is `-`, and is the
+/// input otherwise.
+pub fn solve_markers(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<String, Box<dyn Error>> {
+    let length = params.get_or("length", 4)?;
+    let all = params.get_or("all", true)?;
+    let positions = match params.get("source") {
+        Some("-") => read_markers(io::stdin().lock(), length, all)?,
+        Some(path) => read_markers(File::open(path)?, length, all)?,
+        None => {
+            let found = markers(bytes(input), length)?;
+            if all {
+                found.collect()
+            } else {
+                found.take(1).collect()
+            }
         }
+    };
+    if positions.is_empty() {
+        return Err("Could not find char group".into());
     }
-    None
+    Ok(positions.iter().join("\n"))
 }
 
-pub fn solve_a(input: impl Iterator<Item = String>) -> Result<usize, &'static str> {
-    let line = input.into_iter().next().expect("No input!");
-    find_window(&line, 4).ok_or("Could not find char group")
+fn read_markers(reader: impl Read, length: usize, all: bool) -> Result<Vec<usize>, Box<dyn Error>> {
+    let mut detector = MarkerDetector::new(length)?;
+    let mut positions = Vec::new();
+    for byte in BufReader::new(reader).bytes() {
+        if let Some(position) = detector.push(byte?) {
+            positions.push(position);
+            if !all {
+                break;
+            }
+        }
+    }
+    Ok(positions)
 }
 
-pub fn solve_b(input: impl Iterator<Item = String>) -> Result<usize, &'static str> {
-    let line = input.into_iter().next().expect("No input!");
-    find_window(&line, 14).ok_or("Could not find char group")
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_solves_the_examples() {
+        let examples = [
+            ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+            ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+            ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+            ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+            ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+        ];
+        for (stream, a, b) in examples {
+            assert_eq!(solve_a(stream.lines().map(String::from)).unwrap(), a);
+            assert_eq!(solve_b(stream.lines().map(String::from)).unwrap(), b);
+        }
+        assert!(solve_a("abcabc".lines().map(String::from)).is_err());
+    }
+
+    #[test]
+    fn it_reads_markers_from_any_stream() {
+        let stream = "aab\ncdd";
+        let positions = read_markers(stream.as_bytes(), 4, true).unwrap();
+        assert_eq!(positions, vec![5, 6]);
+        assert_eq!(read_markers(stream.as_bytes(), 4, false).unwrap(), vec![5]);
+        let params = Params::from_args(&["length=3"]);
+        assert_eq!(
+            solve_markers(stream.lines().map(String::from), &params).unwrap(),
+            "4\n5\n6"
+        );
+    }
 }
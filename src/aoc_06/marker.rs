@@ -0,0 +1,103 @@
+/// Finds markers in a stream of bytes, one byte at a time. A marker ends at
+/// each position where the last `length` bytes are all different.
+#[derive(Debug, Clone)]
+pub struct MarkerDetector {
+    length: usize,
+    /// The number of bytes read when each byte value was last seen, or zero
+    /// if it hasn't been.
+    last_seen: [usize; 256],
+    /// The number of bytes read so far.
+    position: usize,
+    /// The start of the longest run of different bytes ending at the current
+    /// position.
+    run_start: usize,
+}
+
+impl MarkerDetector {
+    pub fn new(length: usize) -> Result<Self, String> {
+        if length == 0 {
+            return Err("Markers must be at least one byte long".to_string());
+        }
+        Ok(MarkerDetector {
+            length,
+            last_seen: [0; 256],
+            position: 0,
+            run_start: 0,
+        })
+    }
+
+    /// Reads the next byte, returning the number of bytes read so far if they
+    /// end with a marker.
+    pub fn push(&mut self, byte: u8) -> Option<usize> {
+        // The run can't include the previous occurrence of this byte
+        self.run_start = self.run_start.max(self.last_seen[byte as usize]);
+        self.position += 1;
+        self.last_seen[byte as usize] = self.position;
+        (self.position - self.run_start >= self.length).then_some(self.position)
+    }
+}
+
+/// The end positions of every marker in a stream of bytes.
+pub struct Markers<I> {
+    bytes: I,
+    detector: MarkerDetector,
+}
+
+impl<I: Iterator<Item = u8>> Iterator for Markers<I> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        loop {
+            if let Some(position) = self.detector.push(self.bytes.next()?) {
+                return Some(position);
+            }
+        }
+    }
+}
+
+pub fn markers<I: IntoIterator<Item = u8>>(
+    bytes: I,
+    length: usize,
+) -> Result<Markers<I::IntoIter>, String> {
+    Ok(Markers {
+        bytes: bytes.into_iter(),
+        detector: MarkerDetector::new(length)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks every window from scratch.
+    fn markers_by_window(s: &str, length: usize) -> Vec<usize> {
+        let bytes = s.as_bytes();
+        (length..=bytes.len())
+            .filter(|&end| {
+                let window = &bytes[end - length..end];
+                (0..length).all(|i| !window[i + 1..].contains(&window[i]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_finds_every_marker() {
+        let stream = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+        let found = markers(stream.bytes(), 4).unwrap().collect::<Vec<_>>();
+        assert_eq!(found[0], 7);
+        assert_eq!(markers(stream.bytes(), 14).unwrap().next(), Some(19));
+        for length in 1..=15 {
+            let found = markers(stream.bytes(), length).unwrap().collect::<Vec<_>>();
+            assert_eq!(found, markers_by_window(stream, length));
+        }
+    }
+
+    #[test]
+    fn it_handles_any_bytes() {
+        let stream = [0, 255, 0, 1, 2, 255, 255];
+        let found = markers(stream, 3).unwrap().collect::<Vec<_>>();
+        assert_eq!(found, vec![4, 5, 6]);
+        assert_eq!(markers(stream, 5).unwrap().next(), None);
+        assert!(markers(stream, 0).is_err());
+    }
+}
@@ -1,48 +1,192 @@
 use itertools::Itertools;
+use std::error::Error;
+use std::fs;
+
+use crate::params::Params;
 
 mod monkeys;
+mod operation;
+mod trace;
 
-pub fn solve_a(input: impl Iterator<Item = String>) -> Result<u64, &'static str> {
-    let monkeys = monkeys::parse(input).ok_or("Could not parse monkeys")?;
+use monkeys::{Relief, Simulation};
+use trace::Trace;
 
-    for _ in 0..20 {
-        for monkey in &monkeys {
-            monkey.take_turn(true, &None);
-        }
+fn run(
+    simulation: &mut Simulation,
+    rounds: usize,
+    relief: Relief,
+    mut trace: Option<&mut Trace>,
+) -> Result<(), String> {
+    for _ in 0..rounds {
+        simulation.round(relief, trace.as_deref_mut())?;
     }
+    Ok(())
+}
 
-    let mut inspection_counts = monkeys
-        .iter()
-        .map(|m| *m.inspection_count.borrow())
-        .collect_vec();
-    inspection_counts.sort_unstable();
+pub fn solve_a(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<u64, Box<dyn Error>> {
+    let rounds: usize = params.get_or("rounds", 20)?;
+    let mut simulation = Simulation::parse(input)?;
 
-    let mut monkey_business = inspection_counts.pop().unwrap();
-    monkey_business = monkey_business * inspection_counts.pop().unwrap();
-    Ok(monkey_business)
+    run(&mut simulation, rounds, Relief::DivideByThree, None)?;
+    Ok(simulation.monkey_business())
 }
 
-pub fn solve_b(input: impl Iterator<Item = String>) -> Result<u64, &'static str> {
-    let monkeys = monkeys::parse(input).ok_or("Could not parse monkeys")?;
+pub fn solve_b(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<u64, Box<dyn Error>> {
+    let rounds: usize = params.get_or("rounds", 10000)?;
+    let mut simulation = Simulation::parse(input)?;
+
+    let relief = Relief::Modulo(simulation.modulus());
+    run(&mut simulation, rounds, relief, None)?;
+    Ok(simulation.monkey_business())
+}
 
-    let mut large_modulo = 1;
-    for monkey in &monkeys {
-        large_modulo *= monkey.test_divisor;
+/// Traces the monkeys each item is thrown to, and its worry level after each
+/// throw, over the number of rounds given by the `rounds` param. With
+/// `relief=false`, worry levels are reduced modulo the test divisors instead
+/// of being divided by three, as in part B.
+///
+/// The trace is returned as CSV, or written to the file given by the `out`
+/// param along with a summary of the most inspected items.
+pub fn solve_trace(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<String, Box<dyn Error>> {
+    let rounds: usize = params.get_or("rounds", 20)?;
+    let relief: bool = params.get_or("relief", true)?;
+    let mut simulation = Simulation::parse(input)?;
+
+    let relief = if relief {
+        Relief::DivideByThree
+    } else {
+        Relief::Modulo(simulation.modulus())
+    };
+    let mut trace = Trace::default();
+    simulation.start_trace(&mut trace);
+    run(&mut simulation, rounds, relief, Some(&mut trace))?;
+
+    let Some(path) = params.get("out") else {
+        return Ok(trace.to_csv());
+    };
+    fs::write(path, trace.to_csv())?;
+    let mut summary = format!("Wrote trace of {} items to {}", trace.items.len(), path);
+    for (id, inspections) in trace
+        .inspections()
+        .into_iter()
+        .enumerate()
+        .sorted_by_key(|(_, inspections)| std::cmp::Reverse(*inspections))
+    {
+        summary.push_str(&format!("\nItem {}: inspected {} times", id, inspections));
     }
-    let large_modulo = Some(large_modulo);
-    for _ in 0..10000 {
-        for monkey in &monkeys {
-            monkey.take_turn(false, &large_modulo);
-        }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Monkey 0:
+  Starting items: 79, 98
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 2
+    If false: throw to monkey 3
+
+Monkey 1:
+  Starting items: 54, 65, 75, 74
+  Operation: new = old + 6
+  Test: divisible by 19
+    If true: throw to monkey 2
+    If false: throw to monkey 0
+
+Monkey 2:
+  Starting items: 79, 60, 97
+  Operation: new = old * old
+  Test: divisible by 13
+    If true: throw to monkey 1
+    If false: throw to monkey 3
+
+Monkey 3:
+  Starting items: 74
+  Operation: new = old + 3
+  Test: divisible by 17
+    If true: throw to monkey 0
+    If false: throw to monkey 1";
+
+    fn example() -> impl Iterator<Item = String> {
+        EXAMPLE.lines().map(String::from)
     }
 
-    let mut inspection_counts = monkeys
-        .iter()
-        .map(|m| *m.inspection_count.borrow())
-        .collect_vec();
-    inspection_counts.sort_unstable();
+    #[test]
+    fn it_runs_a() {
+        assert_eq!(solve_a(example(), &Params::default()).unwrap(), 10605);
+    }
+
+    #[test]
+    fn it_runs_b() {
+        assert_eq!(solve_b(example(), &Params::default()).unwrap(), 2713310158);
+    }
+
+    #[test]
+    fn it_traces_items() {
+        let params = Params::parse("small", ["rounds=1".to_string()].into_iter()).unwrap();
+        let csv = solve_trace(example(), &params).unwrap();
+        let lines = csv.lines().collect_vec();
+        assert_eq!(lines[0], "item,hop,round,monkey,worry");
+        // The first item is thrown from monkey 0 to monkey 3, then to monkey 1
+        assert_eq!(lines[1..4], ["0,0,0,0,79", "0,1,1,3,500", "0,2,1,1,167"]);
+        assert_eq!(lines.len(), 1 + 10 + 14);
+    }
+
+    #[test]
+    fn it_snapshots_rounds() {
+        let mut simulation = Simulation::parse(example()).unwrap();
+        simulation.round(Relief::DivideByThree, None).unwrap();
+        assert_eq!(
+            simulation.to_string(),
+            "Monkey 0: 20, 23, 27, 26
+Monkey 1: 2080, 25, 167, 207, 401, 1046
+Monkey 2: 
+Monkey 3: 
+"
+        );
 
-    let mut monkey_business = inspection_counts.pop().unwrap();
-    monkey_business = monkey_business * inspection_counts.pop().unwrap();
-    Ok(monkey_business)
+        let snapshot = simulation.clone();
+        run(&mut simulation, 19, Relief::DivideByThree, None).unwrap();
+        assert_eq!(simulation.inspection_counts, vec![101, 95, 7, 105]);
+        assert_eq!(snapshot.rounds, 1);
+        assert_eq!(snapshot.inspection_counts, vec![2, 4, 3, 5]);
+    }
+
+    #[test]
+    fn it_keeps_the_items_when_an_operation_fails() {
+        let input = EXAMPLE.replace("79, 98", "79, 18446744073709551615");
+        let mut simulation = Simulation::parse(input.lines().map(String::from)).unwrap();
+        let before = simulation.clone();
+        assert_eq!(
+            simulation.round(Relief::DivideByThree, None).unwrap_err(),
+            "Worry level overflowed"
+        );
+        assert_eq!(simulation.items, before.items);
+        assert_eq!(simulation.inspection_counts, vec![0; 4]);
+    }
+
+    #[test]
+    fn it_rejects_invalid_throw_targets() {
+        let input = EXAMPLE.replace("throw to monkey 0", "throw to monkey 4");
+        let result = Simulation::parse(input.lines().map(String::from));
+        assert_eq!(
+            result.unwrap_err(),
+            "Monkey 1 throws to monkey 4, which does not exist"
+        );
+
+        let input = EXAMPLE.replace("true: throw to monkey 2", "true: throw to monkey 0");
+        let result = Simulation::parse(input.lines().map(String::from));
+        assert_eq!(result.unwrap_err(), "Monkey 0 throws to itself");
+    }
 }
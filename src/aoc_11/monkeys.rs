@@ -1,128 +1,247 @@
-use std::collections::VecDeque;
-use std::{cell::RefCell, rc::Weak};
-use std::{collections::HashMap, rc::Rc};
-
-#[derive(Debug)]
-pub enum Operation {
-    Square,
-    Add(u64),
-    Multiply(u64),
+use std::fmt;
+
+use super::operation::Operation;
+use super::trace::Trace;
+
+/// An item, identified by the order in which it appears in the starting
+/// items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Item {
+    pub id: usize,
+    pub worry: u64,
 }
 
-#[derive(Debug)]
+/// How a monkey decides what to do with an item. Throw targets are indices
+/// into the monkeys, and are checked when the monkeys are parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Monkey {
-    items: RefCell<VecDeque<u64>>,
-    operation: Operation,
+    pub operation: Operation,
     pub test_divisor: u64,
-    true_monkey: RefCell<Weak<Monkey>>,
-    false_monkey: RefCell<Weak<Monkey>>,
-    pub inspection_count: RefCell<u64>,
+    pub true_monkey: usize,
+    pub false_monkey: usize,
 }
 
-mod builder {
-    use std::collections::VecDeque;
+/// How worry levels are kept manageable after each inspection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relief {
+    DivideByThree,
+    /// Reduce worry levels modulo a multiple of all of the test divisors.
+    Modulo(u64),
+}
 
+/// The items held by each monkey and the number of items that each monkey
+/// has inspected, after some number of rounds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Simulation {
+    pub monkeys: Vec<Monkey>,
+    pub items: Vec<Vec<Item>>,
+    pub inspection_counts: Vec<u64>,
+    pub rounds: usize,
+}
+
+mod builder {
     use super::Operation;
 
-    pub fn parse_items(line: &str) -> Option<VecDeque<u64>> {
-        let mut items: VecDeque<u64> = VecDeque::new();
-        for x in line.strip_prefix("  Starting items: ")?.split(", ") {
-            items.push_back(x.parse().ok()?)
-        }
-        Some(items)
+    fn error(line: &str) -> String {
+        format!("Could not parse line: {:?}", line)
     }
 
-    pub fn parse_operation(line: &str) -> Option<Operation> {
-        let stripped_line = line.strip_prefix("  Operation: new = old ")?;
-        if stripped_line.starts_with("* old") {
-            return Some(Operation::Square);
-        }
-        if stripped_line.starts_with("*") {
-            let factor = stripped_line.split_once("* ")?.1.parse().ok()?;
-            return Some(Operation::Multiply(factor));
+    pub fn parse_header(line: &str) -> Result<usize, String> {
+        let idx = line
+            .strip_prefix("Monkey ")
+            .and_then(|x| x.strip_suffix(':'));
+        idx.and_then(|x| x.parse().ok()).ok_or_else(|| error(line))
+    }
+
+    pub fn parse_items(line: &str) -> Result<Vec<u64>, String> {
+        let items = line
+            .strip_prefix("  Starting items:")
+            .ok_or_else(|| error(line))?
+            .trim();
+        if items.is_empty() {
+            return Ok(Vec::new());
         }
-        if stripped_line.starts_with("+") {
-            let sum = stripped_line.split_once("+ ")?.1.parse().ok()?;
-            return Some(Operation::Add(sum));
+        items
+            .split(", ")
+            .map(|x| x.parse().map_err(|_| error(line)))
+            .collect()
+    }
+
+    pub fn parse_operation(line: &str) -> Result<Operation, String> {
+        let stripped_line = line
+            .strip_prefix("  Operation: new = ")
+            .ok_or_else(|| error(line))?;
+        stripped_line
+            .parse()
+            .map_err(|e| format!("{}: {:?}", e, line))
+    }
+
+    pub fn parse_test(line: &str) -> Result<u64, String> {
+        let stripped_line = line.strip_prefix("  Test: divisible by ");
+        match stripped_line.and_then(|x| x.parse().ok()) {
+            Some(0) | None => Err(error(line)),
+            Some(divisor) => Ok(divisor),
         }
-        None
     }
 
-    pub fn parse_test(line: &str) -> Option<u64> {
-        let stripped_line = line.strip_prefix("  Test: divisible by ")?;
-        let divisor = stripped_line.parse().ok()?;
-        Some(divisor)
+    pub fn parse_throw(line: &str, condition: &str) -> Result<usize, String> {
+        let prefix = format!("    If {}: throw to monkey ", condition);
+        let monkey_idx = line.strip_prefix(&prefix).and_then(|x| x.parse().ok());
+        monkey_idx.ok_or_else(|| error(line))
     }
+}
 
-    pub fn parse_throw(line: &str) -> Option<usize> {
-        let monkey_idx = line.split_once(" throw to monkey ")?.1.parse().ok()?;
-        Some(monkey_idx)
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
     }
 }
 
-pub fn parse(mut input: impl Iterator<Item = String>) -> Option<Vec<Rc<Monkey>>> {
-    let mut monkeys: Vec<Rc<Monkey>> = Vec::new();
-    let mut monkey_true_destinations: HashMap<usize, usize> = HashMap::new();
-    let mut monkey_false_destinations: HashMap<usize, usize> = HashMap::new();
-    let mut monkey_idx = 0;
-    loop {
-        if input.next().is_none() {
-            break;
+impl Simulation {
+    pub fn parse(mut input: impl Iterator<Item = String>) -> Result<Simulation, String> {
+        let mut monkeys = Vec::new();
+        let mut items = Vec::new();
+        let mut item_count = 0;
+        let mut next_line = || input.next().ok_or("Unexpected end of input");
+        while let Ok(header) = next_line() {
+            let idx = builder::parse_header(&header)?;
+            if idx != monkeys.len() {
+                return Err(format!(
+                    "Expected monkey {}, found monkey {}",
+                    monkeys.len(),
+                    idx
+                ));
+            }
+            let worries = builder::parse_items(&next_line()?)?;
+            items.push(
+                worries
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, worry)| Item {
+                        id: item_count + i,
+                        worry,
+                    })
+                    .collect::<Vec<_>>(),
+            );
+            item_count += items[idx].len();
+            monkeys.push(Monkey {
+                operation: builder::parse_operation(&next_line()?)?,
+                test_divisor: builder::parse_test(&next_line()?)?,
+                true_monkey: builder::parse_throw(&next_line()?, "true")?,
+                false_monkey: builder::parse_throw(&next_line()?, "false")?,
+            });
+            match next_line() {
+                Ok(line) if line.is_empty() => continue,
+                Ok(line) => return Err(format!("Expected a blank line, found: {:?}", line)),
+                Err(_) => break,
+            }
+        }
+        if monkeys.is_empty() {
+            return Err("There are no monkeys".to_string());
+        }
+
+        for (idx, monkey) in monkeys.iter().enumerate() {
+            for target in [monkey.true_monkey, monkey.false_monkey] {
+                if target >= monkeys.len() {
+                    return Err(format!(
+                        "Monkey {} throws to monkey {}, which does not exist",
+                        idx, target
+                    ));
+                }
+                if target == idx {
+                    return Err(format!("Monkey {} throws to itself", idx));
+                }
+            }
         }
-        let items = RefCell::new(builder::parse_items(&input.next()?)?);
-        let operation = builder::parse_operation(&input.next()?)?;
-        let test_divisor = builder::parse_test(&input.next()?)?;
-        monkey_true_destinations.insert(monkey_idx, builder::parse_throw(&input.next()?)?);
-        monkey_false_destinations.insert(monkey_idx, builder::parse_throw(&input.next()?)?);
-        monkeys.push(Rc::new(Monkey {
+        Ok(Simulation {
+            inspection_counts: vec![0; monkeys.len()],
+            monkeys,
             items,
-            operation,
-            test_divisor,
-            true_monkey: RefCell::new(Weak::new()),
-            false_monkey: RefCell::new(Weak::new()),
-            inspection_count: RefCell::new(0),
-        }));
-        monkey_idx += 1;
-        input.next();
+            rounds: 0,
+        })
     }
-    for (i, monkey) in monkeys.iter().enumerate() {
-        *monkey.true_monkey.borrow_mut() = Rc::downgrade(&monkeys[monkey_true_destinations[&i]]);
-        *monkey.false_monkey.borrow_mut() = Rc::downgrade(&monkeys[monkey_false_destinations[&i]]);
+
+    /// The least common multiple of the test divisors. Reducing worry levels
+    /// modulo this doesn't change the result of any test.
+    pub fn modulus(&self) -> u64 {
+        self.monkeys.iter().fold(1, |m, monkey| {
+            m / gcd(m, monkey.test_divisor) * monkey.test_divisor
+        })
     }
-    Some(monkeys)
-}
 
-impl Monkey {
-    pub fn take_turn(&self, divide_by_three: bool, mod_by_large_number: &Option<u64>) {
-        while !self.items.borrow().is_empty() {
-            let mut item = self.items.borrow_mut().pop_front().unwrap();
-            item = match self.operation {
-                Operation::Square => item * item,
-                Operation::Add(x) => item + x,
-                Operation::Multiply(x) => item * x,
-            };
-            if divide_by_three {
-                item = item / 3;
+    /// Records where each item starts in the trace.
+    pub fn start_trace(&self, trace: &mut Trace) {
+        for (idx, items) in self.items.iter().enumerate() {
+            for item in items {
+                trace.record(item, self.rounds, idx);
             }
-            // A number `x` passes the test if and only if `x + k * test_divisor` also passes the test, for any `k`.
-            // This means we can mod out by the product of the prime factors.
-            if let Some(modulo) = mod_by_large_number {
-                item = item % modulo;
+        }
+    }
+
+    /// Runs a single round, in which each monkey takes a turn. If an
+    /// operation fails, the monkey whose turn it is keeps all of its items.
+    pub fn round(&mut self, relief: Relief, mut trace: Option<&mut Trace>) -> Result<(), String> {
+        self.rounds += 1;
+        for idx in 0..self.monkeys.len() {
+            // Work out where every item goes before throwing any of them, so
+            // that a failed operation doesn't lose the rest
+            let monkey = &self.monkeys[idx];
+            let thrown = self.items[idx]
+                .iter()
+                .map(|item| {
+                    // A number `x` passes the test if and only if `x + k * test_divisor` also passes the test, for any `k`.
+                    // This means we can mod out by a multiple of all the divisors,
+                    // as long as the operation only adds, subtracts and multiplies.
+                    let worry = match relief {
+                        Relief::DivideByThree => monkey.operation.apply(item.worry, None)? / 3,
+                        Relief::Modulo(m) => monkey.operation.apply(item.worry, Some(m))?,
+                    };
+                    let target = if worry.is_multiple_of(monkey.test_divisor) {
+                        monkey.true_monkey
+                    } else {
+                        monkey.false_monkey
+                    };
+                    Ok((Item { worry, ..*item }, target))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+
+            // Monkeys never throw to themselves, so the list can be emptied
+            // before the items land with the other monkeys
+            self.items[idx].clear();
+            self.inspection_counts[idx] += thrown.len() as u64;
+            for (item, target) in thrown {
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.record(&item, self.rounds, target);
+                }
+                self.items[target].push(item);
             }
-            let test_result = item % self.test_divisor == 0;
-            let monkey = if test_result {
-                &self.true_monkey
-            } else {
-                &self.false_monkey
-            };
-            monkey
-                .borrow()
-                .upgrade()
-                .unwrap()
-                .items
-                .borrow_mut()
-                .push_back(item);
-            *self.inspection_count.borrow_mut() += 1;
         }
+        Ok(())
+    }
+
+    /// The product of the two highest inspection counts.
+    pub fn monkey_business(&self) -> u64 {
+        let mut inspection_counts = self.inspection_counts.clone();
+        inspection_counts.sort_unstable_by(|a, b| b.cmp(a));
+        inspection_counts.iter().take(2).product()
+    }
+}
+
+impl fmt::Display for Simulation {
+    /// Shows the worry levels of the items held by each monkey, as in the
+    /// puzzle text.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, items) in self.items.iter().enumerate() {
+            let worries = items.iter().map(|item| item.worry.to_string());
+            writeln!(
+                f,
+                "Monkey {}: {}",
+                idx,
+                worries.collect::<Vec<_>>().join(", ")
+            )?;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,238 @@
+use std::{iter::Peekable, str::FromStr};
+
+/// The expression that a monkey uses to work out the new worry level of an
+/// item from the old one, e.g. `old * (old + 3) - 2`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Operation {
+    Old,
+    Constant(u64),
+    Add(Box<Operation>, Box<Operation>),
+    Subtract(Box<Operation>, Box<Operation>),
+    Multiply(Box<Operation>, Box<Operation>),
+}
+
+impl Operation {
+    /// Evaluates the expression for the old worry level.
+    ///
+    /// Addition, subtraction and multiplication all respect modular
+    /// arithmetic, so if a modulus is given the result is reduced by it at
+    /// every step. Otherwise, the result must not overflow or become negative.
+    pub fn apply(&self, old: u64, modulus: Option<u64>) -> Result<u64, &'static str> {
+        Ok(match self {
+            Operation::Old | Operation::Constant(_) => {
+                let value = match self {
+                    Operation::Constant(x) => *x,
+                    _ => old,
+                };
+                match modulus {
+                    Some(m) if value >= m => value % m,
+                    _ => value,
+                }
+            }
+            Operation::Add(a, b) => {
+                let (a, b) = (a.apply(old, modulus)?, b.apply(old, modulus)?);
+                match modulus {
+                    Some(m) => {
+                        // Both are less than m, so the sum wraps at most once
+                        let (sum, overflowed) = a.overflowing_add(b);
+                        if overflowed || sum >= m {
+                            sum.wrapping_sub(m)
+                        } else {
+                            sum
+                        }
+                    }
+                    None => a.checked_add(b).ok_or("Worry level overflowed")?,
+                }
+            }
+            Operation::Subtract(a, b) => {
+                let (a, b) = (a.apply(old, modulus)?, b.apply(old, modulus)?);
+                match modulus {
+                    Some(_) if a >= b => a - b,
+                    Some(m) => a + (m - b),
+                    None => a.checked_sub(b).ok_or("Worry level became negative")?,
+                }
+            }
+            Operation::Multiply(a, b) => {
+                let (a, b) = (a.apply(old, modulus)?, b.apply(old, modulus)?);
+                match modulus {
+                    // Products of residues only fit in a u64 for small moduli
+                    Some(m) if m <= 1 << 32 => a * b % m,
+                    Some(m) => ((a as u128 * b as u128) % m as u128) as u64,
+                    None => a.checked_mul(b).ok_or("Worry level overflowed")?,
+                }
+            }
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Token {
+    Old,
+    Number(u64),
+    Plus,
+    Minus,
+    Times,
+    Open,
+    Close,
+}
+
+fn tokenise(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            ' ' => {
+                chars.next();
+            }
+            '+' | '-' | '*' | '(' | ')' => {
+                chars.next();
+                tokens.push(match c {
+                    '+' => Token::Plus,
+                    '-' => Token::Minus,
+                    '*' => Token::Times,
+                    '(' => Token::Open,
+                    _ => Token::Close,
+                });
+            }
+            '0'..='9' => {
+                let mut digits = String::new();
+                while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    digits.push(d);
+                    chars.next();
+                }
+                let number = digits
+                    .parse()
+                    .map_err(|_| format!("Number too large: {}", digits))?;
+                tokens.push(Token::Number(number));
+            }
+            'a'..='z' => {
+                let mut word = String::new();
+                while let Some(&l) = chars.peek().filter(|l| l.is_ascii_lowercase()) {
+                    word.push(l);
+                    chars.next();
+                }
+                if word != "old" {
+                    return Err(format!("Unknown variable: {}", word));
+                }
+                tokens.push(Token::Old);
+            }
+            _ => return Err(format!("Unexpected character: {}", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+type Tokens = Peekable<std::vec::IntoIter<Token>>;
+
+/// sum := product (("+" | "-") product)*
+fn parse_sum(tokens: &mut Tokens) -> Result<Operation, String> {
+    let mut operation = parse_product(tokens)?;
+    while let Some(&token) = tokens.peek() {
+        let combine = match token {
+            Token::Plus => Operation::Add,
+            Token::Minus => Operation::Subtract,
+            _ => break,
+        };
+        tokens.next();
+        operation = combine(Box::new(operation), Box::new(parse_product(tokens)?));
+    }
+    Ok(operation)
+}
+
+/// product := term ("*" term)*
+fn parse_product(tokens: &mut Tokens) -> Result<Operation, String> {
+    let mut operation = parse_term(tokens)?;
+    while tokens.next_if_eq(&Token::Times).is_some() {
+        operation = Operation::Multiply(Box::new(operation), Box::new(parse_term(tokens)?));
+    }
+    Ok(operation)
+}
+
+/// term := "old" | number | "(" sum ")"
+fn parse_term(tokens: &mut Tokens) -> Result<Operation, String> {
+    match tokens.next() {
+        Some(Token::Old) => Ok(Operation::Old),
+        Some(Token::Number(x)) => Ok(Operation::Constant(x)),
+        Some(Token::Open) => {
+            let operation = parse_sum(tokens)?;
+            match tokens.next() {
+                Some(Token::Close) => Ok(operation),
+                _ => Err("Expected a closing bracket".to_string()),
+            }
+        }
+        Some(token) => Err(format!("Unexpected token: {:?}", token)),
+        None => Err("Unexpected end of expression".to_string()),
+    }
+}
+
+impl FromStr for Operation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = tokenise(s)?.into_iter().peekable();
+        let operation = parse_sum(&mut tokens)?;
+        if let Some(token) = tokens.next() {
+            return Err(format!("Unexpected token: {:?}", token));
+        }
+        Ok(operation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_the_puzzle_operations() {
+        let old = || Box::new(Operation::Old);
+        let constant = |x| Box::new(Operation::Constant(x));
+        assert_eq!("old * old".parse(), Ok(Operation::Multiply(old(), old())));
+        assert_eq!("old + 6".parse(), Ok(Operation::Add(old(), constant(6))));
+        assert_eq!(
+            "2 * old".parse(),
+            Ok(Operation::Multiply(constant(2), old()))
+        );
+    }
+
+    #[test]
+    fn it_respects_precedence_and_brackets() {
+        let operation: Operation = "old * old + 3".parse().unwrap();
+        assert_eq!(operation.apply(5, None), Ok(28));
+        let operation: Operation = "old * (old + 3) - 2 * (1 + 1)".parse().unwrap();
+        assert_eq!(operation.apply(5, None), Ok(36));
+        let operation: Operation = "10 - 3 - 2".parse().unwrap();
+        assert_eq!(operation.apply(0, None), Ok(5));
+    }
+
+    #[test]
+    fn it_rejects_invalid_expressions() {
+        assert!("old * ".parse::<Operation>().is_err());
+        assert!("(old + 1".parse::<Operation>().is_err());
+        assert!("old + 1)".parse::<Operation>().is_err());
+        assert!("new / 2".parse::<Operation>().is_err());
+        assert!("old old".parse::<Operation>().is_err());
+    }
+
+    #[test]
+    fn it_reduces_by_the_modulus() {
+        let operation: Operation = "old * old - 7 + (old - 20)".parse().unwrap();
+        for old in [0, 5, 12, 100, 1234] {
+            let old_i = old as i64;
+            let exact = old_i * old_i - 7 + (old_i - 20);
+            let reduced = operation.apply(old % 13, Some(13)).unwrap();
+            assert_eq!(reduced as i64, exact.rem_euclid(13));
+        }
+        assert!(operation.apply(3, None).is_err());
+    }
+
+    #[test]
+    fn it_reduces_by_large_moduli() {
+        let operation: Operation = "old * old + (old - 3)".parse().unwrap();
+        let m = u64::MAX - 58;
+        for old in [0, 2, 1 << 40, m - 1] {
+            let exact = old as u128 * old as u128 + old as u128 + m as u128 - 3;
+            let reduced = operation.apply(old, Some(m)).unwrap();
+            assert_eq!(reduced as u128, exact % m as u128);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use super::monkeys::Item;
+
+/// A monkey that an item was thrown to, and the item's worry level when it
+/// arrived. Round 0 is the monkey that holds the item at the start.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Hop {
+    pub round: usize,
+    pub monkey: usize,
+    pub worry: u64,
+}
+
+/// The hops that each item makes, indexed by item.
+#[derive(Debug, Default)]
+pub struct Trace {
+    pub items: Vec<Vec<Hop>>,
+}
+
+impl Trace {
+    pub fn record(&mut self, item: &Item, round: usize, monkey: usize) {
+        if self.items.len() <= item.id {
+            self.items.resize(item.id + 1, Vec::new());
+        }
+        self.items[item.id].push(Hop {
+            round,
+            monkey,
+            worry: item.worry,
+        });
+    }
+
+    /// The number of times each item was inspected.
+    pub fn inspections(&self) -> Vec<usize> {
+        self.items.iter().map(|hops| hops.len() - 1).collect()
+    }
+
+    /// One row per hop, with the columns `item,hop,round,monkey,worry`.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("item,hop,round,monkey,worry\n");
+        for (id, hops) in self.items.iter().enumerate() {
+            for (idx, hop) in hops.iter().enumerate() {
+                csv.push_str(&format!(
+                    "{},{},{},{},{}\n",
+                    id, idx, hop.round, hop.monkey, hop.worry
+                ));
+            }
+        }
+        csv
+    }
+}
@@ -1,7 +1,13 @@
 use itertools::Itertools;
 use regex::Regex;
-use std::collections::HashSet;
 use std::error::Error;
+use std::fs;
+use std::ops::RangeInclusive;
+
+use crate::intervals::IntervalSet;
+use crate::params::Params;
+
+mod render;
 
 #[derive(Debug, PartialEq)]
 struct Sensor {
@@ -36,91 +42,250 @@ fn parse_line(input: &str) -> Result<Sensor, Box<dyn Error>> {
     })
 }
 
-fn count_beaconless_in_row(sensor_beacons: &Vec<Sensor>, row: i32) -> usize {
-    let mut ranges_in_row = Vec::new();
-    for sensor in sensor_beacons.iter() {
-        let distance_remaining = sensor.distance - (sensor.location.1 - row).abs();
-        if distance_remaining >= 0 {
-            ranges_in_row.push((
-                sensor.location.0 - distance_remaining,
-                sensor.location.0 + distance_remaining + 1,
-            ));
-        }
-    }
+/// The columns in the row that are within range of a sensor.
+fn covered_in_row(sensor_beacons: &[Sensor], row: i32) -> IntervalSet {
+    sensor_beacons
+        .iter()
+        .filter_map(|sensor| {
+            let distance_remaining = sensor.distance - (sensor.location.1 - row).abs();
+            if distance_remaining < 0 {
+                return None;
+            }
+            let col = sensor.location.0 as i64;
+            let distance_remaining = distance_remaining as i64;
+            Some(col - distance_remaining..col + distance_remaining + 1)
+        })
+        .collect()
+}
 
-    // Brute force solution
-    let mut cols_where_beacon_not_present = HashSet::new();
-    for (start, end) in ranges_in_row {
-        for i in start..end {
-            cols_where_beacon_not_present.insert(i);
-        }
-    }
+fn count_beaconless_in_row(sensor_beacons: &[Sensor], row: i32) -> i64 {
+    let mut covered = covered_in_row(sensor_beacons, row);
     for sensor in sensor_beacons.iter() {
         if sensor.beacon.1 == row {
-            cols_where_beacon_not_present.remove(&sensor.beacon.0);
+            covered.remove_point(sensor.beacon.0 as i64);
         }
     }
-    cols_where_beacon_not_present.len()
+    covered.len()
 }
 
-fn find_beaconless_in_row(sensor_beacons: &Vec<Sensor>, row: i32, max: i32) -> Option<i32> {
-    let mut ranges_in_row = Vec::new();
-    for sensor in sensor_beacons.iter() {
-        let distance_remaining = sensor.distance - (sensor.location.1 - row).abs();
-        if distance_remaining >= 0 {
-            ranges_in_row.push((
-                sensor.location.0 - distance_remaining,
-                sensor.location.0 + distance_remaining + 1,
-            ));
-        }
-    }
-    ranges_in_row.sort();
+/// The coordinates of a point after rotating the grid by 45 degrees, so that
+/// the area covered by a sensor becomes a square: `u = x + y`, `v = x - y`.
+fn rotate((x, y): (i32, i32)) -> (i64, i64) {
+    (x as i64 + y as i64, x as i64 - y as i64)
+}
 
-    let mut end = ranges_in_row[0].1;
-    let last_idx = ranges_in_row.len();
-    for idx in 1..last_idx {
-        let x = ranges_in_row[idx].0;
-        if x > end && x >= 0 && x < max {
-            return Some(x - 1);
-        }
-        end = end.max(ranges_in_row[idx].1);
+/// Finds every point in the rectangle with columns `x` and rows `y` that is
+/// out of range of all of the sensors, one at a time, as there can be far too
+/// many to hold at once.
+///
+/// In rotated coordinates, the edges of the sensors' squares split the search
+/// area into a grid of cells, each of which is either covered by a sensor or
+/// not. Only the uncovered cells need to be searched for points.
+fn find_beaconless(
+    sensors: &[Sensor],
+    x: RangeInclusive<i32>,
+    y: RangeInclusive<i32>,
+) -> impl Iterator<Item = (i32, i32)> {
+    let is_empty = x.is_empty() || y.is_empty();
+    let (x_min, x_max) = (*x.start() as i64, *x.end() as i64);
+    let (y_min, y_max) = (*y.start() as i64, *y.end() as i64);
+    let u_bounds = (x_min + y_min, x_max + y_max + 1);
+    let v_bounds = (x_min - y_max, x_max - y_min + 1);
+    let mut u_edges = vec![u_bounds.0, u_bounds.1];
+    let mut v_edges = vec![v_bounds.0, v_bounds.1];
+    for sensor in sensors {
+        let (u, v) = rotate(sensor.location);
+        let distance = sensor.distance as i64;
+        u_edges.extend([u - distance, u + distance + 1]);
+        v_edges.extend([v - distance, v + distance + 1]);
     }
-    None
+    let cells = |mut edges: Vec<i64>, bounds: (i64, i64)| {
+        edges.retain(|edge| (bounds.0..=bounds.1).contains(edge));
+        edges.sort();
+        edges.dedup();
+        edges
+            .into_iter()
+            .tuple_windows()
+            .map(|(start, end)| (start, end - 1))
+            .collect_vec()
+    };
+    let u_cells = cells(u_edges, u_bounds);
+    let v_cells = cells(v_edges, v_bounds);
+
+    let uncovered = u_cells
+        .into_iter()
+        .cartesian_product(v_cells)
+        .filter(|&((u_start, _), (v_start, _))| {
+            !is_empty
+                && !sensors.iter().any(|sensor| {
+                    let (u, v) = rotate(sensor.location);
+                    let distance = sensor.distance as i64;
+                    (u_start - u).abs() <= distance && (v_start - v).abs() <= distance
+                })
+        })
+        .collect_vec();
+
+    uncovered
+        .into_iter()
+        .flat_map(move |((u_start, u_end), (v_start, v_end))| {
+            // Clip the cell to the values of u where some v in the cell maps
+            // back to a point in the search area
+            let u_from = u_start.max(v_start + 2 * y_min).max(2 * x_min - v_end);
+            let u_to = u_end.min(2 * x_max - v_start).min(v_end + 2 * y_max);
+            (u_from..=u_to).flat_map(move |u| {
+                let v_from = v_start.max(2 * x_min - u).max(u - 2 * y_max);
+                let v_to = v_end.min(2 * x_max - u).min(u - 2 * y_min);
+                // u and v must have the same parity to map back to a point
+                let v_from = v_from + (u - v_from).rem_euclid(2);
+                (v_from..=v_to)
+                    .step_by(2)
+                    .map(move |v| (((u + v) / 2) as i32, ((u - v) / 2) as i32))
+            })
+        })
 }
-fn find_beaconless(sensors: &Vec<Sensor>, max: i32) -> Option<(i32, i32)> {
-    for row in 0..max {
-        if let Some(col) = find_beaconless_in_row(&sensors, row, max) {
-            return Some((col, row));
+
+/// Where to look for positions that cannot contain a beacon.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SearchArea {
+    /// The row in which beaconless positions are counted.
+    pub row: i32,
+    /// The distress beacon has both coordinates in `min..=max`.
+    pub min: i32,
+    pub max: i32,
+    /// The tuning frequency is `x * multiplier + y`.
+    pub multiplier: i64,
+}
+
+impl SearchArea {
+    /// The search area from the puzzle text for the small example, or from
+    /// the puzzle itself for any other input.
+    pub fn for_size(size: &str) -> Self {
+        match size {
+            "small" => SearchArea {
+                row: 10,
+                min: 0,
+                max: 20,
+                multiplier: 4000000,
+            },
+            _ => SearchArea {
+                row: 2000000,
+                min: 0,
+                max: 4000000,
+                multiplier: 4000000,
+            },
         }
     }
-    None
+
+    /// Overrides the defaults with any of the params `row`, `min`, `max` and
+    /// `multiplier`.
+    pub fn with_params(self, params: &Params) -> Result<Self, Box<dyn Error>> {
+        Ok(SearchArea {
+            row: params.get_or("row", self.row)?,
+            min: params.get_or("min", self.min)?,
+            max: params.get_or("max", self.max)?,
+            multiplier: params.get_or("multiplier", self.multiplier)?,
+        })
+    }
+}
+
+fn parse_sensors(input: impl Iterator<Item = String>) -> Result<Vec<Sensor>, Box<dyn Error>> {
+    input.map(|x| parse_line(&x)).collect()
 }
 
-pub fn solve_a(input: impl Iterator<Item = String>) -> Result<usize, Box<dyn Error>> {
-    let sensors = (input
-        .map(|x| parse_line(&x))
-        .collect::<Result<Vec<Sensor>, _>>())?;
+pub fn solve_a(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<i64, Box<dyn Error>> {
+    let area = SearchArea::for_size(&params.size).with_params(params)?;
+    let sensors = parse_sensors(input)?;
 
-    let count = count_beaconless_in_row(&sensors, 2000000);
+    let count = count_beaconless_in_row(&sensors, area.row);
     Ok(count)
 }
 
-// Brute-force solution. Took ~10s to run.
-pub fn solve_b(input: impl Iterator<Item = String>) -> Result<i64, Box<dyn Error>> {
-    let sensors = (input
-        .map(|x| parse_line(&x))
-        .collect::<Result<Vec<Sensor>, _>>())?;
+pub fn solve_b(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<i64, Box<dyn Error>> {
+    let area = SearchArea::for_size(&params.size).with_params(params)?;
+    let sensors = parse_sensors(input)?;
 
-    let beaconless = find_beaconless(&sensors, 4000000).expect("Should have found a beacon");
-    println!("Found beacon: {:?}", beaconless);
+    // A second candidate is enough to know the answer is ambiguous
+    let beaconless = find_beaconless(&sensors, area.min..=area.max, area.min..=area.max)
+        .take(2)
+        .collect_vec();
+    for point in &beaconless {
+        println!("Found beacon: {:?}", point);
+    }
+    match beaconless[..] {
+        [beacon] => Ok((beacon.0 as i64) * area.multiplier + (beacon.1 as i64)),
+        [] => Err("Could not find the distress beacon".into()),
+        _ => Err("Found more than one possible distress beacon".into()),
+    }
+}
 
-    Ok((beaconless.0 as i64) * 4000000 + (beaconless.1 as i64))
+/// Draws the sensors' coverage of the search area to a PPM image, at the path
+/// given by the `out` param.
+pub fn solve_render(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<String, Box<dyn Error>> {
+    let area = SearchArea::for_size(&params.size).with_params(params)?;
+    let viewport = render::Viewport::around(&area).with_params(params)?;
+    let path = params.get("out").unwrap_or("aoc_15.ppm");
+    let sensors = parse_sensors(input)?;
+
+    // Only the distress beacons that will be drawn are needed, and only up to
+    // a limit, as a large area can have far too many to mark
+    let x = area.min.max(viewport.x_min)..=area.max.min(viewport.x_max);
+    let y = area.min.max(viewport.y_min)..=area.max.min(viewport.y_max);
+    let beaconless = find_beaconless(&sensors, x, y)
+        .take(render::MAX_DISTRESS_BEACONS + 1)
+        .collect_vec();
+    let shown = &beaconless[..beaconless.len().min(render::MAX_DISTRESS_BEACONS)];
+    let image = render::render(&sensors, &area, shown, &viewport);
+    fs::write(path, image.to_ppm())?;
+    let mut message = format!("Wrote {}x{} image to {}", image.width, image.height, path);
+    if shown.len() < beaconless.len() {
+        message += &format!(", marking only {} possible distress beacons", shown.len());
+    }
+    Ok(message)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE: [&str; 14] = [
+        "Sensor at x=2, y=18: closest beacon is at x=-2, y=15",
+        "Sensor at x=9, y=16: closest beacon is at x=10, y=16",
+        "Sensor at x=13, y=2: closest beacon is at x=15, y=3",
+        "Sensor at x=12, y=14: closest beacon is at x=10, y=16",
+        "Sensor at x=10, y=20: closest beacon is at x=10, y=16",
+        "Sensor at x=14, y=17: closest beacon is at x=10, y=16",
+        "Sensor at x=8, y=7: closest beacon is at x=2, y=10",
+        "Sensor at x=2, y=0: closest beacon is at x=2, y=10",
+        "Sensor at x=0, y=11: closest beacon is at x=2, y=10",
+        "Sensor at x=20, y=14: closest beacon is at x=25, y=17",
+        "Sensor at x=17, y=20: closest beacon is at x=21, y=22",
+        "Sensor at x=16, y=7: closest beacon is at x=15, y=3",
+        "Sensor at x=14, y=3: closest beacon is at x=15, y=3",
+        "Sensor at x=20, y=1: closest beacon is at x=15, y=3",
+    ];
+
+    fn find_beaconless_by_row(sensors: &[Sensor], min: i32, max: i32) -> Vec<(i32, i32)> {
+        (min..=max)
+            .flat_map(|row| {
+                covered_in_row(sensors, row)
+                    .gaps(min as i64..max as i64 + 1)
+                    .into_iter()
+                    .flatten()
+                    .map(move |col| (col as i32, row))
+            })
+            .sorted()
+            .collect()
+    }
+
     #[test]
     fn it_parses_a_line() {
         let input = "Sensor at x=168575, y=491461: closest beacon is at x=1053731, y=-142061";
@@ -160,4 +325,98 @@ mod tests {
         let result = count_beaconless_in_row(&input, 10);
         assert_eq!(result, 26);
     }
+
+    #[test]
+    fn it_finds_the_distress_beacon() {
+        let input = EXAMPLE
+            .map(|x| parse_line(x).unwrap())
+            .into_iter()
+            .collect_vec();
+
+        assert_eq!(
+            find_beaconless(&input, 0..=20, 0..=20).collect_vec(),
+            vec![(14, 11)]
+        );
+        assert_eq!(
+            find_beaconless(&input, -5..=25, -5..=25)
+                .sorted()
+                .collect_vec(),
+            find_beaconless_by_row(&input, -5, 25)
+        );
+        // Only some of the points found in a rectangle
+        let expected = find_beaconless_by_row(&input, -5, 25)
+            .into_iter()
+            .filter(|&(x, y)| (-3..=7).contains(&x) && (10..=25).contains(&y))
+            .collect_vec();
+        assert_eq!(
+            find_beaconless(&input, -3..=7, 10..=25)
+                .sorted()
+                .collect_vec(),
+            expected
+        );
+    }
+
+    #[test]
+    fn it_finds_all_beaconless_squares() {
+        let input = [
+            "Sensor at x=0, y=0: closest beacon is at x=1, y=1",
+            "Sensor at x=10, y=3: closest beacon is at x=10, y=5",
+        ]
+        .map(|x| parse_line(x).unwrap())
+        .into_iter()
+        .collect_vec();
+
+        let result = find_beaconless(&input, 0..=10, 0..=10)
+            .sorted()
+            .collect_vec();
+        assert_eq!(result.len(), 121 - 6 - 9);
+        assert!(result.contains(&(3, 0)));
+        assert!(!result.contains(&(2, 0)));
+        assert_eq!(result, find_beaconless_by_row(&input, 0, 10));
+        assert_eq!(find_beaconless(&input, 0..=0, 0..=0).count(), 0);
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = find_beaconless(&input, 5..=4, 0..=10).count();
+        assert_eq!(empty, 0);
+    }
+
+    #[test]
+    fn it_runs_a_and_b_on_the_example() {
+        let params = Params::parse("small", std::iter::empty()).unwrap();
+        let result = solve_a(EXAMPLE.map(String::from).into_iter(), &params).unwrap();
+        assert_eq!(result, 26);
+        let result = solve_b(EXAMPLE.map(String::from).into_iter(), &params).unwrap();
+        assert_eq!(result, 56000011);
+    }
+
+    #[test]
+    fn it_stops_after_a_second_candidate() {
+        // Almost all of this area is out of range of the sensors
+        let args = ["max=2000000000"].map(String::from).into_iter();
+        let params = Params::parse("small", args).unwrap();
+        let error = solve_b(EXAMPLE.map(String::from).into_iter(), &params).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Found more than one possible distress beacon"
+        );
+    }
+
+    #[test]
+    fn it_overrides_the_search_area_with_params() {
+        let args = ["row=11", "max=30", "multiplier=10"]
+            .map(String::from)
+            .into_iter();
+        let params = Params::parse("small", args).unwrap();
+        let area = SearchArea::for_size(&params.size)
+            .with_params(&params)
+            .unwrap();
+        assert_eq!(
+            area,
+            SearchArea {
+                row: 11,
+                min: 0,
+                max: 30,
+                multiplier: 10,
+            }
+        );
+    }
 }
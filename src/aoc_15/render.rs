@@ -0,0 +1,263 @@
+use std::error::Error;
+
+use super::{SearchArea, Sensor};
+use crate::params::Params;
+
+const UNCOVERED: [u8; 3] = [16, 16, 24];
+const OUTSIDE_AREA: [u8; 3] = [64, 64, 64];
+const SENSOR: [u8; 3] = [220, 40, 40];
+const BEACON: [u8; 3] = [40, 80, 220];
+const DISTRESS_BEACON: [u8; 3] = [40, 220, 40];
+
+/// The most pixels an image can have, which keeps the image under 100MB.
+const MAX_PIXELS: f64 = 25_000_000.0;
+
+/// The most distress beacons to mark, as a large search area can have far
+/// more possible positions than could be drawn.
+pub const MAX_DISTRESS_BEACONS: usize = 1000;
+
+/// The part of the grid to draw, and how many units of the grid each pixel
+/// covers.
+#[derive(Debug, PartialEq)]
+pub struct Viewport {
+    pub x_min: i32,
+    pub y_min: i32,
+    pub x_max: i32,
+    pub y_max: i32,
+    pub scale: f64,
+}
+
+impl Viewport {
+    /// Shows the whole search area in an image roughly 800 pixels wide.
+    pub fn around(area: &SearchArea) -> Self {
+        Viewport {
+            x_min: area.min,
+            y_min: area.min,
+            x_max: area.max,
+            y_max: area.max,
+            scale: (area.max - area.min + 1) as f64 / 800.0,
+        }
+    }
+
+    /// Overrides the defaults with any of the params `x_min`, `y_min`,
+    /// `x_max`, `y_max` and `scale`.
+    pub fn with_params(self, params: &Params) -> Result<Self, Box<dyn Error>> {
+        let viewport = Viewport {
+            x_min: params.get_or("x_min", self.x_min)?,
+            y_min: params.get_or("y_min", self.y_min)?,
+            x_max: params.get_or("x_max", self.x_max)?,
+            y_max: params.get_or("y_max", self.y_max)?,
+            scale: params.get_or("scale", self.scale)?,
+        };
+        if viewport.x_max < viewport.x_min || viewport.y_max < viewport.y_min {
+            return Err("The viewport is empty".into());
+        }
+        if viewport.scale <= 0.0 || !viewport.scale.is_finite() {
+            return Err("The scale must be positive".into());
+        }
+        let (width, height) = viewport.size_f64();
+        if width * height > MAX_PIXELS {
+            return Err(format!(
+                "The image would be {} by {} pixels, which is too large; increase the scale",
+                width, height
+            )
+            .into());
+        }
+        Ok(viewport)
+    }
+
+    /// The width and height of the image, as floats so that huge viewports
+    /// can be checked without overflowing.
+    fn size_f64(&self) -> (f64, f64) {
+        let pixels = |min: i32, max: i32| ((max as f64 - min as f64 + 1.0) / self.scale).ceil();
+        (
+            pixels(self.x_min, self.x_max),
+            pixels(self.y_min, self.y_max),
+        )
+    }
+
+    fn size(&self) -> (usize, usize) {
+        let (width, height) = self.size_f64();
+        (width as usize, height as usize)
+    }
+
+    /// The point of the grid in the middle of the pixel.
+    fn to_point(&self, col: usize, row: usize) -> (i32, i32) {
+        let coordinate = |min: i32, pixel: usize| {
+            (min as f64 + (pixel as f64 + 0.5) * self.scale).floor() as i32
+        };
+        (coordinate(self.x_min, col), coordinate(self.y_min, row))
+    }
+
+    fn to_pixel(&self, (x, y): (i32, i32)) -> Option<(usize, usize)> {
+        if x < self.x_min || x > self.x_max || y < self.y_min || y > self.y_max {
+            return None;
+        }
+        let pixel = |min: i32, value: i32| ((value as f64 - min as f64) / self.scale) as usize;
+        Some((pixel(self.x_min, x), pixel(self.y_min, y)))
+    }
+}
+
+/// An RGB raster image.
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Image {
+    fn new(width: usize, height: usize) -> Self {
+        Image {
+            width,
+            height,
+            pixels: vec![UNCOVERED; width * height],
+        }
+    }
+
+    fn set(&mut self, col: usize, row: usize, colour: [u8; 3]) {
+        self.pixels[row * self.width + col] = colour;
+    }
+
+    /// Draws a square centred on the pixel, clipped to the image.
+    fn mark(&mut self, (col, row): (usize, usize), radius: usize, colour: [u8; 3]) {
+        for r in row.saturating_sub(radius)..=(row + radius).min(self.height - 1) {
+            for c in col.saturating_sub(radius)..=(col + radius).min(self.width - 1) {
+                self.set(c, r, colour);
+            }
+        }
+    }
+
+    /// Encodes the image as a binary PPM file.
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        for pixel in &self.pixels {
+            ppm.extend(pixel);
+        }
+        ppm
+    }
+}
+
+/// Draws the area covered by the sensors, shading each pixel by the number of
+/// sensors in range of it, and marks the sensors, their beacons and any
+/// distress beacons.
+pub fn render(
+    sensors: &[Sensor],
+    area: &SearchArea,
+    distress_beacons: &[(i32, i32)],
+    viewport: &Viewport,
+) -> Image {
+    let (width, height) = viewport.size();
+    let mut image = Image::new(width, height);
+    for row in 0..height {
+        for col in 0..width {
+            let point = viewport.to_point(col, row);
+            let in_range = sensors
+                .iter()
+                .filter(|sensor| super::get_distance(sensor.location, point) <= sensor.distance)
+                .count();
+            let in_area = (area.min..=area.max).contains(&point.0)
+                && (area.min..=area.max).contains(&point.1);
+            let colour = match in_range {
+                0 if in_area => UNCOVERED,
+                0 => OUTSIDE_AREA,
+                n => {
+                    let shade = (200 - 25 * n.min(6)) as u8;
+                    [shade, shade, 255]
+                }
+            };
+            image.set(col, row, colour);
+        }
+    }
+
+    // Markers are a few pixels across, or fill the square when zoomed in
+    let radius = ((0.5 / viewport.scale) as usize).max(2);
+    let markers = sensors
+        .iter()
+        .map(|sensor| (sensor.beacon, BEACON))
+        .chain(sensors.iter().map(|sensor| (sensor.location, SENSOR)))
+        .chain(
+            distress_beacons
+                .iter()
+                .map(|&point| (point, DISTRESS_BEACON)),
+        );
+    for (point, colour) in markers {
+        if let Some(pixel) = viewport.to_pixel(point) {
+            image.mark(pixel, radius, colour);
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_line;
+    use super::*;
+
+    fn pixel(image: &Image, col: usize, row: usize) -> [u8; 3] {
+        image.pixels[row * image.width + col]
+    }
+
+    #[test]
+    fn it_renders_coverage_and_markers() {
+        let sensors =
+            vec![parse_line("Sensor at x=2, y=2: closest beacon is at x=2, y=0").unwrap()];
+        let area = SearchArea {
+            row: 0,
+            min: 0,
+            max: 9,
+            multiplier: 1,
+        };
+        let viewport = Viewport {
+            scale: 0.2,
+            ..Viewport::around(&area)
+        };
+        let image = render(&sensors, &area, &[(9, 9)], &viewport);
+
+        assert_eq!((image.width, image.height), (50, 50));
+        assert_eq!(pixel(&image, 12, 12), SENSOR);
+        assert_eq!(pixel(&image, 12, 2), BEACON);
+        assert_eq!(pixel(&image, 47, 47), DISTRESS_BEACON);
+        assert_eq!(pixel(&image, 22, 12), [175, 175, 255]);
+        assert_eq!(pixel(&image, 30, 30), UNCOVERED);
+        assert!(image.to_ppm().starts_with(b"P6\n50 50\n255\n"));
+        assert_eq!(image.to_ppm().len(), 13 + 50 * 50 * 3);
+    }
+
+    #[test]
+    fn it_finds_pixels_across_the_whole_grid() {
+        let viewport = Viewport {
+            x_min: i32::MIN,
+            y_min: i32::MIN,
+            x_max: i32::MAX,
+            y_max: i32::MAX,
+            scale: 2f64.powi(32) / 10.0,
+        };
+        assert_eq!(viewport.size(), (10, 10));
+        assert_eq!(viewport.to_pixel((i32::MAX, 0)), Some((9, 5)));
+        assert_eq!(viewport.to_pixel((i32::MIN, i32::MIN)), Some((0, 0)));
+    }
+
+    #[test]
+    fn it_rejects_empty_viewports() {
+        let args = ["x_min=5", "x_max=4"].map(String::from).into_iter();
+        let params = Params::parse("small", args).unwrap();
+        let area = SearchArea::for_size("small");
+        assert!(Viewport::around(&area).with_params(&params).is_err());
+    }
+
+    #[test]
+    fn it_rejects_huge_images() {
+        let params =
+            |args: &[&str]| Params::parse("large", args.iter().map(|x| x.to_string())).unwrap();
+        let area = SearchArea::for_size("large");
+        let viewport = Viewport::around(&area);
+        assert!(viewport.with_params(&params(&["scale=1"])).is_err());
+        let viewport = Viewport::around(&area);
+        // Far too wide, where the width doesn't even fit in an i32
+        assert!(viewport
+            .with_params(&params(&["x_min=-2147483648"]))
+            .is_err());
+        let viewport = Viewport::around(&area);
+        assert!(viewport.with_params(&params(&["scale=1000"])).is_ok());
+    }
+}
@@ -1,130 +1,418 @@
-use std::{
-    collections::HashMap,
-    error::Error,
-    io::{self, Write},
-    ops::Div,
-};
+use std::{collections::HashMap, error::Error, str::FromStr};
 
 use crate::aoc_16::volcano::Volcano;
-use itertools::Itertools;
+use crate::params::Params;
 
+mod graph;
+mod schedule;
 mod volcano;
 
+use self::schedule::{Opening, Schedule};
 use self::volcano::Valve;
 
+/// Someone opening valves (i.e. me or an elephant), starting at a valve with
+/// some time to do it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Agent {
+    pub start: Valve,
+    pub time: i32,
+}
+
+impl FromStr for Agent {
+    type Err = Box<dyn Error>;
+
+    /// Parses an agent like `AA:26`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, time) = s
+            .split_once(':')
+            .ok_or("Agent should be written as <valve>:<time>")?;
+        if start.chars().count() != 2 {
+            return Err("Valve name should be a string with two chars".into());
+        }
+        Ok(Agent {
+            start: start.into(),
+            time: time.parse()?,
+        })
+    }
+}
+
+/// An agent's position, the valves it has opened (as a bitmask over
+/// `Volcano.valves`), and the time it has left.
+type State = (Valve, u32, i32);
+
+/// The best ways for a single agent to open each set of valves.
+struct Routes {
+    agent: Agent,
+    /// The most pressure released for each set of opened valves, and the state
+    /// at the end of the route which releases it.
+    best: Vec<Option<(i32, State)>>,
+    /// The previous state on the best route to each state.
+    previous: HashMap<State, (i32, Option<State>)>,
+}
+
+impl Routes {
+    fn pressure(&self, opened: usize) -> i32 {
+        self.best[opened].map_or(0, |(pressure, _)| pressure)
+    }
+
+    /// Follows the best route for opening this set of valves back to the start.
+    fn schedule(&self, opened: usize, volcano: &Volcano) -> Schedule {
+        let mut openings = Vec::new();
+        let mut state = self.best[opened].map(|(_, state)| state);
+        while let Some(current) = state {
+            let (valve, _, time_left) = current;
+            let (_, previous) = self.previous[&current];
+            if previous.is_some() {
+                openings.push(Opening {
+                    valve,
+                    minute: self.agent.time - time_left,
+                    pressure: time_left * volcano.flows[&valve],
+                });
+            }
+            state = previous;
+        }
+        openings.reverse();
+        Schedule {
+            agent: self.agent,
+            openings,
+        }
+    }
+}
+
 impl Volcano {
-    fn solve(&self, start: Valve, time_left: i32) -> i32 {
-        // println!(
-        //     "Solving with start {} and remaining time {}",
-        //     start, time_left
-        // );
-        // dbg!(&self.flows);
-        let mut destinations = Vec::new();
-
-        for (valve, flow) in self.flows.borrow().iter() {
-            if start != *valve {
-                let distance = self.distances[&(start, *valve)];
-                if distance < time_left - 1 {
-                    let cost = distance + 1;
-                    let gain = (time_left - cost) * flow;
-                    if gain > 0 {
-                        destinations.push((*valve, cost, gain));
-                    }
+    /// Calculates the best routes for opening each set of valves within the
+    /// agent's time limit.
+    ///
+    /// This steps forward through the (position, opened valves, time left)
+    /// states reachable from the start, only continuing from a state if it
+    /// hasn't already been reached with at least as much pressure released.
+    fn best_routes(&self, agent: &Agent) -> Routes {
+        let start = (agent.start, 0_u32, agent.time);
+        let mut previous: HashMap<State, (i32, Option<State>)> = HashMap::new();
+        previous.insert(start, (0, None));
+        let mut states = vec![(start, 0)];
+
+        while let Some((state, pressure)) = states.pop() {
+            let (position, opened, time_left) = state;
+            if previous[&state].0 > pressure {
+                // A better route to this state has been found since
+                continue;
+            }
+
+            for (idx, valve) in self.valves.iter().enumerate() {
+                if opened & (1 << idx) != 0 {
+                    continue;
+                }
+                let distance = if *valve == position {
+                    0
+                } else {
+                    self.distances[&(position, *valve)]
+                };
+                let cost = distance + 1;
+                if cost >= time_left {
+                    continue;
+                }
+                let next_time_left = time_left - cost;
+                let next_pressure = pressure + next_time_left * self.flows[valve];
+                let next_state = (*valve, opened | (1 << idx), next_time_left);
+                if previous
+                    .get(&next_state)
+                    .is_none_or(|(best, _)| *best < next_pressure)
+                {
+                    previous.insert(next_state, (next_pressure, Some(state)));
+                    states.push((next_state, next_pressure));
                 }
             }
         }
 
-        let mut max_flow = 0;
-        for (valve, cost, gain) in destinations {
-            let flow_value;
-            {
-                flow_value = self
-                    .flows
-                    .borrow_mut()
-                    .remove(&valve)
-                    .expect("Valve should be in hash map");
+        let mut best: Vec<Option<(i32, State)>> = vec![None; 1 << self.valves.len()];
+        for (state, (pressure, _)) in previous.iter() {
+            let entry = &mut best[state.1 as usize];
+            if entry.is_none_or(|best| best < (*pressure, *state)) {
+                *entry = Some((*pressure, *state));
             }
-            let potential_gain = gain + self.solve(valve, time_left - cost);
-            max_flow = max_flow.max(potential_gain);
-            self.flows.borrow_mut().insert(valve, flow_value);
         }
 
-        max_flow
+        Routes {
+            agent: *agent,
+            best,
+            previous,
+        }
     }
 
-    fn solve_with_elephant(&self, start: Valve, time_left: i32) -> i32 {
-        // Splits the volcano flows into two. With 16 nodes, there are 65,536
-        // partitions, which takes about 30 seconds when built with the release
-        // profile. Good enough. ¯\_(ツ)_/¯
-        let original_flows = self.flows.borrow().clone();
-
-        let mut best_score = 0;
-        let mut iterations = 0;
-        let total_iterations = original_flows.keys().powerset().collect_vec().len();
-        let step = total_iterations.div(1000);
-
-        for elephant_flows_valves in original_flows.keys().powerset() {
-            let mut elephant_flow = HashMap::new();
-            for valve in elephant_flows_valves.iter() {
-                elephant_flow.insert(
-                    **valve,
-                    self.flows
-                        .borrow_mut()
-                        .remove(valve)
-                        .expect("Valve should be in hash map"),
-                );
-            }
-            let my_best_score = self.solve(start, time_left);
-            {
-                *self.flows.borrow_mut() = elephant_flow;
-            }
-            let elephant_best_score = self.solve(start, time_left);
-            best_score = best_score.max(my_best_score + elephant_best_score);
-            *self.flows.borrow_mut() = original_flows.clone();
-            iterations += 1;
-            if iterations % step == 0 {
-                print!(".");
-                io::stdout().flush().expect("Flush should succeed");
+    /// The agents open disjoint sets of valves. Going through the agents one
+    /// at a time, this keeps a table of the most pressure that the agents so
+    /// far can release together by opening any subset of each set of valves,
+    /// along with the valves the latest agent opens to get it.
+    fn solve_with_agents(&self, agents: &[Agent]) -> (i32, Vec<Schedule>) {
+        let all_valves = (1_usize << self.valves.len()) - 1;
+        let mut best_combined = vec![0; all_valves + 1];
+        let mut all_routes = Vec::new();
+        let mut all_choices = Vec::new();
+
+        for agent in agents {
+            let routes = self.best_routes(agent);
+            let mut next_best_combined = vec![0; all_valves + 1];
+            let mut choices = vec![0; all_valves + 1];
+            for valves in 0..=all_valves {
+                // Tries each subset of these valves for this agent to open
+                let mut opened = valves;
+                loop {
+                    let pressure = routes.pressure(opened) + best_combined[valves ^ opened];
+                    if pressure > next_best_combined[valves] {
+                        next_best_combined[valves] = pressure;
+                        choices[valves] = opened;
+                    }
+                    if opened == 0 {
+                        break;
+                    }
+                    opened = (opened - 1) & valves;
+                }
             }
+            best_combined = next_best_combined;
+            all_routes.push(routes);
+            all_choices.push(choices);
         }
 
-        best_score
+        // Works back through the agents to find which valves each one opened
+        let mut schedules = Vec::new();
+        let mut valves = all_valves;
+        for (routes, choices) in all_routes.iter().zip(all_choices.iter()).rev() {
+            let opened = choices[valves];
+            schedules.push(routes.schedule(opened, self));
+            valves ^= opened;
+        }
+        schedules.reverse();
+
+        (best_combined[all_valves], schedules)
     }
 }
 
-pub fn solve_a(input: impl Iterator<Item = String>) -> Result<i32, Box<dyn Error>> {
-    let volcano = Volcano::build(input)?;
-    let soln = volcano.solve("AA".into(), 30);
-    Ok(soln)
+/// Opens the valves with the given agents, written as `<valve>:<time>` and
+/// separated by commas (e.g. `agents=AA:26,AA:26`).
+fn solve_with_params(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+    default_agents: Vec<Agent>,
+) -> Result<(Volcano, i32, Vec<Schedule>), Box<dyn Error>> {
+    let agents = params.get_list_or("agents", default_agents)?;
+    let starts = agents.iter().map(|agent| agent.start).collect::<Vec<_>>();
+    let volcano = Volcano::build(input, &starts)?;
+    let (pressure, schedules) = volcano.solve_with_agents(&agents);
+    Ok((volcano, pressure, schedules))
+}
+
+pub fn solve_a(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<i32, Box<dyn Error>> {
+    let me = Agent {
+        start: "AA".into(),
+        time: 30,
+    };
+    let (_, pressure, _) = solve_with_params(input, params, vec![me])?;
+    Ok(pressure)
 }
 
-pub fn solve_b(input: impl Iterator<Item = String>) -> Result<i32, Box<dyn Error>> {
-    let volcano = Volcano::build(input)?;
-    let soln = volcano.solve_with_elephant("AA".into(), 26);
-    Ok(soln)
+pub fn solve_b(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<i32, Box<dyn Error>> {
+    let me = Agent {
+        start: "AA".into(),
+        time: 26,
+    };
+    let (_, pressure, _) = solve_with_params(input, params, vec![me, me])?;
+    Ok(pressure)
+}
+
+/// Describes the best schedule for opening the valves minute-by-minute,
+/// checking it against the distances between the valves.
+///
+/// Params:
+/// - `agents`: as for part A and B (default `AA:30`)
+pub fn solve_schedule(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<String, Box<dyn Error>> {
+    let me = Agent {
+        start: "AA".into(),
+        time: 30,
+    };
+    let (volcano, pressure, schedules) = solve_with_params(input, params, vec![me])?;
+    if schedule::verify_all(&schedules, &volcano)? != pressure {
+        return Err("Schedule does not release the expected pressure".into());
+    }
+    Ok(schedule::narrate(&schedules, &volcano))
+}
+
+/// Renders the network of tunnels as a Graphviz graph, highlighting the best
+/// schedule for opening the valves.
+///
+/// Params:
+/// - `agents`: as for part A and B (default `AA:30`)
+/// - `compressed`: if true, only includes the valves with a non-zero flow,
+///   with edges weighted by distance (default false)
+pub fn solve_graph(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<String, Box<dyn Error>> {
+    let me = Agent {
+        start: "AA".into(),
+        time: 30,
+    };
+    let (volcano, _, schedules) = solve_with_params(input, params, vec![me])?;
+    if params.get_or("compressed", false)? {
+        Ok(graph::to_compressed_dot(&volcano, &schedules))
+    } else {
+        Ok(graph::to_dot(&volcano, &schedules))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE: [&str; 10] = [
+        "Valve AA has flow rate=0; tunnels lead to valves DD, II, BB",
+        "Valve BB has flow rate=13; tunnels lead to valves CC, AA",
+        "Valve CC has flow rate=2; tunnels lead to valves DD, BB",
+        "Valve DD has flow rate=20; tunnels lead to valves CC, AA, EE",
+        "Valve EE has flow rate=3; tunnels lead to valves FF, DD",
+        "Valve FF has flow rate=0; tunnels lead to valves EE, GG",
+        "Valve GG has flow rate=0; tunnels lead to valves FF, HH",
+        "Valve HH has flow rate=22; tunnel leads to valve GG",
+        "Valve II has flow rate=0; tunnels lead to valves AA, JJ",
+        "Valve JJ has flow rate=21; tunnel leads to valve II",
+    ];
+
     #[test]
     fn it_runs_a() {
-        let lines = [
-            "Valve AA has flow rate=0; tunnels lead to valves DD, II, BB",
-            "Valve BB has flow rate=13; tunnels lead to valves CC, AA",
-            "Valve CC has flow rate=2; tunnels lead to valves DD, BB",
-            "Valve DD has flow rate=20; tunnels lead to valves CC, AA, EE",
-            "Valve EE has flow rate=3; tunnels lead to valves FF, DD",
-            "Valve FF has flow rate=0; tunnels lead to valves EE, GG",
-            "Valve GG has flow rate=0; tunnels lead to valves FF, HH",
-            "Valve HH has flow rate=22; tunnel leads to valve GG",
-            "Valve II has flow rate=0; tunnels lead to valves AA, JJ",
-            "Valve JJ has flow rate=21; tunnel leads to valve II",
-        ]
-        .map(String::from)
-        .into_iter();
-        let result = solve_a(lines).unwrap();
+        let lines = EXAMPLE.map(String::from).into_iter();
+        let result = solve_a(lines, &Params::default()).unwrap();
         assert_eq!(result, 1651);
     }
+
+    #[test]
+    fn it_runs_b() {
+        let lines = EXAMPLE.map(String::from).into_iter();
+        let result = solve_b(lines, &Params::default()).unwrap();
+        assert_eq!(result, 1707);
+    }
+
+    #[test]
+    fn it_parses_an_agent() {
+        let agent: Agent = "JJ:12".parse().unwrap();
+        assert_eq!(
+            agent,
+            Agent {
+                start: "JJ".into(),
+                time: 12
+            }
+        );
+        assert!("JJ".parse::<Agent>().is_err());
+        assert!("JJJ:12".parse::<Agent>().is_err());
+    }
+
+    #[test]
+    fn it_runs_with_other_agents() {
+        let lines = || EXAMPLE.map(String::from).into_iter();
+        let params = |agents: &str| {
+            Params::parse("small", [format!("agents={}", agents)].into_iter()).unwrap()
+        };
+
+        // A single agent with 30 minutes from AA should match part A
+        let result = solve_b(lines(), &params("AA:30")).unwrap();
+        assert_eq!(result, 1651);
+
+        // Opening JJ straight away releases 21 * 2, and then there's no time to
+        // open anything else
+        let result = solve_b(lines(), &params("JJ:3")).unwrap();
+        assert_eq!(result, 42);
+
+        // Adding more agents can only help
+        let one_agent = solve_b(lines(), &params("AA:26")).unwrap();
+        let two_agents = solve_b(lines(), &params("AA:26,FF:10")).unwrap();
+        let three_agents = solve_b(lines(), &params("AA:26,FF:10,II:10")).unwrap();
+        assert!(two_agents > one_agent);
+        assert!(three_agents >= two_agents);
+
+        assert!(solve_b(lines(), &params("ZZ:30")).is_err());
+    }
+
+    #[test]
+    fn it_finds_a_schedule() {
+        let lines = EXAMPLE.map(String::from).into_iter();
+        let me = Agent {
+            start: "AA".into(),
+            time: 30,
+        };
+        let volcano = Volcano::build(lines, &[me.start]).unwrap();
+        let (pressure, schedules) = volcano.solve_with_agents(&[me]);
+        assert_eq!(pressure, 1651);
+
+        // The same order as in the puzzle text
+        let valves = schedules[0]
+            .openings
+            .iter()
+            .map(|opening| (opening.valve, opening.minute))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            valves,
+            vec![
+                ("DD".into(), 2),
+                ("BB".into(), 5),
+                ("JJ".into(), 9),
+                ("HH".into(), 17),
+                ("EE".into(), 21),
+                ("CC".into(), 24),
+            ]
+        );
+        assert_eq!(schedule::verify_all(&schedules, &volcano).unwrap(), 1651);
+
+        let narrative = schedule::narrate(&schedules, &volcano);
+        assert!(
+            narrative.contains("== Minute 2 ==\nNo valves are open.\nAgent 1 opens valve DD.\n")
+        );
+        assert!(narrative.ends_with("Total pressure released: 1651"));
+
+        // Tampering with the schedule should be caught
+        let mut wrong = schedules.clone();
+        wrong[0].openings[0].minute = 1;
+        assert!(schedule::verify_all(&wrong, &volcano).is_err());
+    }
+
+    #[test]
+    fn it_finds_schedules_for_two_agents() {
+        let lines = EXAMPLE.map(String::from).into_iter();
+        let params = Params::parse(
+            "small",
+            ["agents=AA:26,AA:26"].map(String::from).into_iter(),
+        )
+        .unwrap();
+        let narrative = solve_schedule(lines, &params).unwrap();
+        assert!(narrative.ends_with("Total pressure released: 1707"));
+    }
+
+    #[test]
+    fn it_renders_graphs() {
+        let lines = || EXAMPLE.map(String::from).into_iter();
+        let dot = solve_graph(lines(), &Params::default()).unwrap();
+        assert!(dot.starts_with("graph volcano {\n"));
+        assert!(dot.contains("  AA [label=\"AA\", shape=doublecircle];\n"));
+        assert!(dot.contains(
+            "  DD [label=\"DD\\nflow 20\\nopened at 2\", color=red, penwidth=3, shape=circle];\n"
+        ));
+        // Each tunnel appears once, and the route from BB to JJ is highlighted
+        assert_eq!(dot.matches(" -- ").count(), 10);
+        assert!(dot.contains("  AA -- II [color=red, penwidth=3];\n"));
+        assert!(dot.contains("  FF -- GG [color=red, penwidth=3];\n"));
+
+        let params =
+            Params::parse("small", ["compressed=true"].map(String::from).into_iter()).unwrap();
+        let dot = solve_graph(lines(), &params).unwrap();
+        assert_eq!(dot.matches(" -- ").count(), 21);
+        assert!(dot.contains("  AA -- HH [label=5];\n"));
+        assert!(dot.contains("  BB -- JJ [label=3, color=red, penwidth=3];\n"));
+    }
 }
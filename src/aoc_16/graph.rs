@@ -0,0 +1,167 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use itertools::Itertools;
+
+use super::schedule::Schedule;
+use super::volcano::{Valve, Volcano};
+
+/// Colours for highlighting each agent's schedule.
+static COLOURS: [&str; 6] = ["red", "blue", "darkgreen", "orange", "purple", "brown"];
+
+fn colour(agent_idx: usize) -> &'static str {
+    COLOURS[agent_idx % COLOURS.len()]
+}
+
+/// Finds a shortest path between two valves through the tunnels, including
+/// both ends.
+fn shortest_path(volcano: &Volcano, from: Valve, to: Valve) -> Vec<Valve> {
+    let mut previous = HashMap::from([(from, from)]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(valve) = queue.pop_front() {
+        if valve == to {
+            break;
+        }
+        for neighbour in &volcano.tunnels[&valve] {
+            if !previous.contains_key(neighbour) {
+                previous.insert(*neighbour, valve);
+                queue.push_back(*neighbour);
+            }
+        }
+    }
+
+    let mut path = vec![to];
+    let mut valve = to;
+    while valve != from {
+        valve = previous[&valve];
+        path.push(valve);
+    }
+    path.reverse();
+    path
+}
+
+fn edge(a: Valve, b: Valve) -> (Valve, Valve) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// The nodes for the valves, with the valves in the schedules highlighted in
+/// the colour of the agent which opens them.
+fn write_nodes(dot: &mut String, volcano: &Volcano, valves: &[Valve], schedules: &[Schedule]) {
+    let mut openings = HashMap::new();
+    let mut starts = HashSet::new();
+    for (idx, schedule) in schedules.iter().enumerate() {
+        starts.insert(schedule.agent.start);
+        for opening in &schedule.openings {
+            openings.insert(opening.valve, (idx, opening.minute));
+        }
+    }
+
+    for valve in valves {
+        let flow = volcano.flows.get(valve).copied().unwrap_or(0);
+        let mut attributes = vec![];
+        match openings.get(valve) {
+            Some((idx, minute)) => {
+                attributes.push(format!(
+                    "label=\"{}\\nflow {}\\nopened at {}\"",
+                    valve, flow, minute
+                ));
+                attributes.push(format!("color={}", colour(*idx)));
+                attributes.push("penwidth=3".to_string());
+            }
+            None if flow > 0 => attributes.push(format!("label=\"{}\\nflow {}\"", valve, flow)),
+            None => attributes.push(format!("label=\"{}\"", valve)),
+        }
+        if starts.contains(valve) {
+            attributes.push("shape=doublecircle".to_string());
+        } else if flow > 0 {
+            attributes.push("shape=circle".to_string());
+        } else {
+            attributes.push("shape=point".to_string());
+            attributes.push("xlabel=\"".to_string() + &valve.to_string() + "\"");
+        }
+        dot.push_str(&format!("  {} [{}];\n", valve, attributes.join(", ")));
+    }
+}
+
+fn write_edge(dot: &mut String, (a, b): (Valve, Valve), label: Option<i32>, agent: Option<usize>) {
+    let mut attributes = vec![];
+    if let Some(label) = label {
+        attributes.push(format!("label={}", label));
+    }
+    if let Some(idx) = agent {
+        attributes.push(format!("color={}", colour(idx)));
+        attributes.push("penwidth=3".to_string());
+    }
+    if attributes.is_empty() {
+        dot.push_str(&format!("  {} -- {};\n", a, b));
+    } else {
+        dot.push_str(&format!("  {} -- {} [{}];\n", a, b, attributes.join(", ")));
+    }
+}
+
+/// Renders the tunnels between all of the valves as a Graphviz graph, with the
+/// routes taken in the schedules highlighted.
+pub fn to_dot(volcano: &Volcano, schedules: &[Schedule]) -> String {
+    let mut highlighted = HashMap::new();
+    for (idx, schedule) in schedules.iter().enumerate() {
+        let mut position = schedule.agent.start;
+        for opening in &schedule.openings {
+            for (a, b) in shortest_path(volcano, position, opening.valve)
+                .into_iter()
+                .tuple_windows()
+            {
+                highlighted.insert(edge(a, b), idx);
+            }
+            position = opening.valve;
+        }
+    }
+
+    let mut dot = String::from("graph volcano {\n");
+    let valves = volcano.tunnels.keys().copied().sorted().collect_vec();
+    write_nodes(&mut dot, volcano, &valves, schedules);
+    let edges = volcano
+        .tunnels
+        .iter()
+        .flat_map(|(a, neighbours)| neighbours.iter().map(|b| edge(*a, *b)))
+        .sorted()
+        .dedup();
+    for e in edges {
+        write_edge(&mut dot, e, None, highlighted.get(&e).copied());
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Renders the compressed network of the valves with a non-zero flow (and the
+/// start valves) as a Graphviz graph, with edges weighted by the distance
+/// between the valves, and the moves in the schedules highlighted.
+pub fn to_compressed_dot(volcano: &Volcano, schedules: &[Schedule]) -> String {
+    let mut highlighted = HashMap::new();
+    for (idx, schedule) in schedules.iter().enumerate() {
+        let mut position = schedule.agent.start;
+        for opening in &schedule.openings {
+            if opening.valve != position {
+                highlighted.insert(edge(position, opening.valve), idx);
+            }
+            position = opening.valve;
+        }
+    }
+
+    let mut dot = String::from("graph volcano {\n");
+    let valves = volcano.flows.keys().copied().sorted().collect_vec();
+    write_nodes(&mut dot, volcano, &valves, schedules);
+    for (a, b) in valves.iter().tuple_combinations() {
+        let e = edge(*a, *b);
+        write_edge(
+            &mut dot,
+            e,
+            Some(volcano.distances[&e]),
+            highlighted.get(&e).copied(),
+        );
+    }
+    dot.push_str("}\n");
+    dot
+}
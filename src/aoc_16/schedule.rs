@@ -0,0 +1,139 @@
+use std::{collections::HashSet, error::Error};
+
+use super::volcano::{Valve, Volcano};
+use super::Agent;
+
+/// A valve being opened, and the total pressure it releases before the
+/// agent's time runs out.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Opening {
+    pub valve: Valve,
+    pub minute: i32,
+    pub pressure: i32,
+}
+
+/// The valves that an agent opens, in order.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Schedule {
+    pub agent: Agent,
+    pub openings: Vec<Opening>,
+}
+
+impl Schedule {
+    pub fn pressure(&self) -> i32 {
+        self.openings.iter().map(|opening| opening.pressure).sum()
+    }
+
+    /// Walks through the schedule using the distances between the valves,
+    /// checking that each valve can be reached and opened at the given minute
+    /// and that it releases the given pressure.
+    pub fn verify(&self, volcano: &Volcano) -> Result<i32, Box<dyn Error>> {
+        let mut position = self.agent.start;
+        let mut minute = 0;
+        for opening in &self.openings {
+            let distance = if opening.valve == position {
+                0
+            } else {
+                *volcano
+                    .distances
+                    .get(&(position, opening.valve))
+                    .ok_or(format!("No route from {} to {}", position, opening.valve))?
+            };
+            minute += distance + 1;
+            if minute != opening.minute || minute >= self.agent.time {
+                return Err(format!(
+                    "{} can be opened at minute {}, not minute {}",
+                    opening.valve, minute, opening.minute
+                )
+                .into());
+            }
+            let flow = volcano.flows.get(&opening.valve).copied().unwrap_or(0);
+            if opening.pressure != flow * (self.agent.time - minute) {
+                return Err(format!("{} releases the wrong pressure", opening.valve).into());
+            }
+            position = opening.valve;
+        }
+        Ok(self.pressure())
+    }
+}
+
+/// Checks each schedule, and that no valve is opened more than once, returning
+/// the total pressure released.
+pub fn verify_all(schedules: &[Schedule], volcano: &Volcano) -> Result<i32, Box<dyn Error>> {
+    let mut opened = HashSet::new();
+    let mut total = 0;
+    for schedule in schedules {
+        total += schedule.verify(volcano)?;
+        for opening in &schedule.openings {
+            if !opened.insert(opening.valve) {
+                return Err(format!("{} is opened more than once", opening.valve).into());
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Describes what each agent does in each minute, and how much pressure is
+/// released, in the style of the puzzle text.
+pub fn narrate(schedules: &[Schedule], volcano: &Volcano) -> String {
+    let max_time = schedules.iter().map(|s| s.agent.time).max().unwrap_or(0);
+    // Each valve releases pressure until its agent's time runs out
+    let mut open_valves: Vec<(Valve, i32)> = Vec::new();
+    let mut total = 0;
+    let mut narrative = String::new();
+
+    for minute in 1..=max_time {
+        narrative.push_str(&format!("== Minute {} ==\n", minute));
+        let releasing = open_valves
+            .iter()
+            .filter(|(_, time)| minute <= *time)
+            .map(|(valve, _)| *valve)
+            .collect::<Vec<_>>();
+        let released: i32 = releasing.iter().map(|v| volcano.flows[v]).sum();
+        total += released;
+        match releasing.len() {
+            0 => narrative.push_str("No valves are open.\n"),
+            1 => narrative.push_str(&format!(
+                "Valve {} is open, releasing {} pressure.\n",
+                releasing[0], released
+            )),
+            _ => narrative.push_str(&format!(
+                "Valves {} are open, releasing {} pressure.\n",
+                releasing
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                released
+            )),
+        }
+
+        for (idx, schedule) in schedules.iter().enumerate() {
+            if minute > schedule.agent.time {
+                continue;
+            }
+            let next = schedule.openings.iter().find(|o| o.minute >= minute);
+            match next {
+                Some(opening) if opening.minute == minute => {
+                    narrative.push_str(&format!(
+                        "Agent {} opens valve {}.\n",
+                        idx + 1,
+                        opening.valve
+                    ));
+                    open_valves.push((opening.valve, schedule.agent.time));
+                }
+                Some(opening) => {
+                    narrative.push_str(&format!(
+                        "Agent {} moves towards valve {}.\n",
+                        idx + 1,
+                        opening.valve
+                    ));
+                }
+                None => {}
+            }
+        }
+        narrative.push('\n');
+    }
+    narrative.push_str(&format!("Total pressure released: {}", total));
+    narrative
+}
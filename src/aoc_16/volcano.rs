@@ -1,13 +1,18 @@
 use itertools::Itertools;
 use regex::Regex;
 use std::{
-    cell::RefCell,
     collections::{HashMap, HashSet, VecDeque},
     error::Error,
     fmt::Display,
 };
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// The most valves with a non-zero flow that can be searched. The routes are
+/// stored for every subset of the valves, and splitting the valves between
+/// agents goes through every pair of disjoint subsets, so the memory and time
+/// needed grow as 2^n and 3^n.
+const MAX_VALVES: usize = 20;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Valve(char, char);
 
 impl From<&str> for Valve {
@@ -112,13 +117,22 @@ fn build_distances(
 }
 
 pub struct Volcano {
-    pub flows: RefCell<HashMap<Valve, i32>>,
+    pub flows: HashMap<Valve, i32>,
+    /// The valves with a non-zero flow, in a fixed order so that a set of them
+    /// can be represented as a bitmask.
+    pub valves: Vec<Valve>,
     pub distances: HashMap<(Valve, Valve), i32>,
+    /// The tunnels leading from every valve, including the trivial ones.
+    pub tunnels: HashMap<Valve, Vec<Valve>>,
 }
 
 impl Volcano {
-    /// Builds the network, keeping only non-trivial nodes, and including distances.
-    pub fn build(input: impl Iterator<Item = String>) -> Result<Volcano, Box<dyn Error>> {
+    /// Builds the network, keeping only non-trivial nodes (those with a flow,
+    /// and the start valves), and including distances.
+    pub fn build(
+        input: impl Iterator<Item = String>,
+        starts: &[Valve],
+    ) -> Result<Volcano, Box<dyn Error>> {
         let (flows, neighbours) = parse_input(input)?;
 
         let mut non_trivial_flows = HashMap::new();
@@ -127,13 +141,35 @@ impl Volcano {
                 non_trivial_flows.insert(*valve, *flow);
             }
         }
-        non_trivial_flows.insert("AA".into(), 0);
+        for start in starts {
+            if !flows.contains_key(start) {
+                return Err(format!("No valve named {}", start).into());
+            }
+            non_trivial_flows.entry(*start).or_insert(0);
+        }
         let non_trivial_nodes = HashSet::from_iter(non_trivial_flows.keys().map(|x| *x));
         let distances = build_distances(&non_trivial_nodes, &neighbours);
+        let valves = non_trivial_flows
+            .iter()
+            .filter(|(_, flow)| **flow > 0)
+            .map(|(valve, _)| *valve)
+            .sorted()
+            .collect_vec();
+        if valves.len() > MAX_VALVES {
+            return Err(format!(
+                "There are {} valves with a non-zero flow, but at most {} can be \
+                 searched, as the search goes through every subset of them",
+                valves.len(),
+                MAX_VALVES
+            )
+            .into());
+        }
 
         Ok(Volcano {
-            flows: RefCell::new(non_trivial_flows),
+            flows: non_trivial_flows,
+            valves,
             distances,
+            tunnels: neighbours,
         })
     }
 }
@@ -194,6 +230,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_rejects_too_many_valves() {
+        let names = (0..=MAX_VALVES)
+            .map(|idx| format!("A{}", (b'A' + idx as u8) as char))
+            .collect::<Vec<_>>();
+        let lines = names.iter().enumerate().map(|(idx, name)| {
+            let next = &names[(idx + 1) % names.len()];
+            format!(
+                "Valve {} has flow rate=1; tunnel leads to valve {}",
+                name, next
+            )
+        });
+        let error = Volcano::build(lines, &["AA".into()]).err().unwrap();
+        assert!(error.to_string().starts_with("There are 21 valves"));
+    }
+
     #[test]
     fn it_builds_distances() {
         let lines = [
@@ -284,8 +336,8 @@ mod tests {
         ]
         .map(String::from)
         .into_iter();
-        let volcano = Volcano::build(lines).unwrap();
-        let flows = volcano.flows.borrow().clone();
+        let volcano = Volcano::build(lines, &["AA".into()]).unwrap();
+        let flows = volcano.flows.clone();
         assert_eq!(
             flows,
             HashMap::from([
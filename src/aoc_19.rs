@@ -1,70 +1,181 @@
-use std::error::Error;
+use std::{collections::HashSet, error::Error};
+
+use crate::params::Params;
 
 mod blueprint;
+mod build_plan;
 mod factory_state;
 mod material;
 
 use blueprint::Blueprint;
+use build_plan::BuildPlan;
 use factory_state::FactoryState;
-use material::Material;
-
-/// Tries each material in turn as the type of next robot to build, and stops if
-/// the sequence takes longer than the max turns to build, or if we can tell
-/// that there's no possible chance of improving the score.
-fn recursively_get_best_score(factory_states: &mut Vec<FactoryState>, best_so_far: &mut i32) {
-    let l = factory_states.len();
-    *best_so_far = factory_states[l - 1].score().max(*best_so_far);
-
-    // Only iterate if there's a chance of improving the score
-    if &factory_states[l - 1].upper_bound_ignoring_ore_and_clay() < best_so_far {
-        return;
+use material::MaterialMap;
+
+/// A depth-first search for the best order in which to build robots.
+struct Search {
+    best: BuildPlan,
+    /// States which have already been explored (or pruned). The best score
+    /// only increases, so there's no need to explore them again.
+    seen: HashSet<(usize, MaterialMap<i32>, MaterialMap<i32>)>,
+    /// Only one robot can be built per turn, so there's no point having more
+    /// robots collecting a material than can be spent in a single turn.
+    max_robots: MaterialMap<i32>,
+}
+
+impl Search {
+    fn new(blueprint: &Blueprint) -> Self {
+        Search {
+            best: BuildPlan::default(),
+            seen: HashSet::new(),
+            max_robots: blueprint.max_spend_rates(),
+        }
+    }
+
+    /// Tries each material in turn as the type of next robot to build, and
+    /// stops if the sequence takes longer than the max turns to build, or if we
+    /// can tell that there's no possible chance of improving the score.
+    fn recursively_get_best_plan(&mut self, factory_states: &mut Vec<FactoryState>) {
+        let l = factory_states.len();
+        if factory_states[l - 1].score() > self.best.score {
+            self.best = BuildPlan::from_states(factory_states);
+        }
+
+        if !self
+            .seen
+            .insert(factory_states[l - 1].cache_key(&self.max_robots))
+        {
+            return;
+        }
+
+        // Only iterate if there's a chance of improving the score
+        if factory_states[l - 1].upper_bound() < self.best.score {
+            return;
+        }
+
+        for material in factory_states[l - 1].each_material() {
+            if factory_states[l - 1].robot_count(&material) >= self.max_robots[&material] {
+                continue;
+            }
+            if let Some(next_state) = factory_states[l - 1].build_next_robot(&material) {
+                factory_states.push(next_state);
+                self.recursively_get_best_plan(factory_states);
+                factory_states.pop();
+            }
+        }
     }
+}
+
+fn get_best_plan(blueprint: &Blueprint, max_turns: &usize) -> BuildPlan {
+    let mut factory_states = Vec::from([FactoryState::new(blueprint, max_turns)]);
+    let mut search = Search::new(blueprint);
+    search.recursively_get_best_plan(&mut factory_states);
+    search.best
+}
+
+/// Parses the blueprints with the given (one-based) numbers, or all of them if
+/// no numbers are given.
+fn parse_blueprints(
+    input: impl Iterator<Item = String>,
+    numbers: Option<Vec<usize>>,
+    target: Option<&str>,
+) -> Result<Vec<(usize, Blueprint)>, Box<dyn Error>> {
+    let lines = input.collect::<Vec<_>>();
+    let numbers = numbers.unwrap_or_else(|| (1..=lines.len()).collect());
 
-    for material in Material::each() {
-        if let Some(next_state) = factory_states[l - 1].build_next_robot(&material) {
-            factory_states.push(next_state);
-            recursively_get_best_score(factory_states, best_so_far);
-            factory_states.pop();
+    let mut blueprints = Vec::new();
+    for number in numbers {
+        let line = number
+            .checked_sub(1)
+            .and_then(|idx| lines.get(idx))
+            .ok_or(format!("No blueprint {}", number))?;
+        let mut blueprint = Blueprint::parse(line.clone())?;
+        if let Some(target) = target {
+            blueprint = blueprint.with_target(target)?;
         }
+        blueprints.push((number, blueprint));
     }
+    Ok(blueprints)
 }
 
-pub fn solve_a(input: impl Iterator<Item = String>) -> Result<i32, Box<dyn Error>> {
-    let blueprints = input
-        .map(Blueprint::parse)
-        .collect::<Result<Vec<Blueprint>, _>>()?;
+/// Params:
+/// - `turns`: the number of turns (default 24)
+/// - `blueprints`: comma-separated (one-based) blueprint numbers (default all)
+/// - `target`: the material to maximise (default geode)
+pub fn solve_a(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<i32, Box<dyn Error>> {
+    let max_turns: usize = params.get_or("turns", 24)?;
+    let numbers = match params.get("blueprints") {
+        Some(_) => Some(params.get_list_or("blueprints", vec![])?),
+        None => None,
+    };
+    let blueprints = parse_blueprints(input, numbers, params.get("target"))?;
 
-    let max_turns = 24;
     let mut quality_levels = Vec::new();
-    for (idx, blueprint) in blueprints.iter().enumerate() {
-        let mut factory_states = Vec::from([FactoryState::new(blueprint, &max_turns)]);
-        let mut best_score = 0;
-        recursively_get_best_score(&mut factory_states, &mut best_score);
-        println!("Best score for {}: {}", idx + 1, best_score);
-        quality_levels.push(best_score * (idx as i32 + 1));
+    for (number, blueprint) in blueprints.iter() {
+        let best_score = get_best_plan(blueprint, &max_turns).score;
+        println!("Best score for {}: {}", number, best_score);
+        quality_levels.push(best_score * *number as i32);
     }
 
     Ok(quality_levels.into_iter().sum())
 }
 
-pub fn solve_b(input: impl Iterator<Item = String>) -> Result<i32, Box<dyn Error>> {
-    let max_turns = 32;
+/// Params:
+/// - `turns`: the number of turns (default 32)
+/// - `blueprints`: comma-separated (one-based) blueprint numbers (default the
+///   first three)
+/// - `target`: the material to maximise (default geode)
+pub fn solve_b(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<i32, Box<dyn Error>> {
+    let max_turns: usize = params.get_or("turns", 32)?;
+    let input = input.collect::<Vec<_>>();
+    let default_numbers = (1..=input.len().min(3)).collect();
+    let numbers = params.get_list_or("blueprints", default_numbers)?;
+    let blueprints = parse_blueprints(input.into_iter(), Some(numbers), params.get("target"))?;
+
     let mut best_scores_product = 1;
-    for (idx, blueprint) in input.map(Blueprint::parse).take(3).enumerate() {
-        let blueprint = blueprint?;
-        let mut factory_states = Vec::from([FactoryState::new(&blueprint, &max_turns)]);
-        let mut best_score = 0;
-        recursively_get_best_score(&mut factory_states, &mut best_score);
-        println!("Best score for {}: {}", idx + 1, best_score);
+    for (number, blueprint) in blueprints.iter() {
+        let best_score = get_best_plan(blueprint, &max_turns).score;
+        println!("Best score for {}: {}", number, best_score);
         best_scores_product *= best_score;
     }
     Ok(best_scores_product)
 }
 
+/// Finds the best plan for a single blueprint and renders a turn-by-turn log
+/// of following it.
+///
+/// Params:
+/// - `blueprint`: the (one-based) blueprint number (default 1)
+/// - `turns`: the number of turns (default 24)
+/// - `target`: the material to maximise (default geode)
+pub fn solve_plan(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<String, Box<dyn Error>> {
+    let number: usize = params.get_or("blueprint", 1)?;
+    let max_turns: usize = params.get_or("turns", 24)?;
+    let (_, blueprint) = parse_blueprints(input, Some(vec![number]), params.get("target"))?
+        .pop()
+        .unwrap();
+
+    let plan = get_best_plan(&blueprint, &max_turns);
+    let mut log = plan.render(&blueprint, &max_turns)?;
+    log.push_str(&format!("Quality level: {}", plan.score * number as i32));
+    Ok(log)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::aoc_19::material::{Material, MaterialMap};
+    use crate::aoc_19::material::Material;
+
+    static BLUEPRINT_WITH_COAL: &str = "Blueprint 1: Each ore robot costs 2 ore. Each coal robot costs 1 ore. Each clay robot costs 3 ore and 1 coal. Each diamond robot costs 2 clay and 2 coal.";
 
     static BLUEPRINT_1 :&str  = "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore and 14 clay. Each geode robot costs 2 ore and 7 obsidian.";
     static BLUEPRINT_2 :&str  = "Blueprint 2: Each ore robot costs 2 ore. Each clay robot costs 3 ore. Each obsidian robot costs 3 ore and 8 clay. Each geode robot costs 3 ore and 12 obsidian.";
@@ -72,14 +183,15 @@ mod tests {
     fn check_soln(
         blueprint: &Blueprint,
         max_turns: &usize,
-        materials: impl Iterator<Item = Material>,
+        materials: impl Iterator<Item = &'static str>,
     ) -> i32 {
         let mut factory_states = Vec::from([FactoryState::new(blueprint, max_turns)]);
         for material in materials {
             {
+                let material = blueprint.material(material).unwrap();
                 if let Some(next_state) = factory_states.last().unwrap().build_next_robot(&material)
                 {
-                    println!("Built robot: {:?}", &material);
+                    println!("Built robot: {}", blueprint.name(&material));
                     factory_states.push(next_state);
                 } else {
                     println!("Warning: could not build robot");
@@ -106,44 +218,63 @@ mod tests {
         assert_eq!(
             blueprint,
             Blueprint {
-                robot_costs: MaterialMap {
-                    ore: MaterialMap {
-                        ore: 4,
-                        ..Default::default()
-                    },
-                    clay: MaterialMap {
-                        ore: 2,
-                        ..Default::default()
-                    },
-                    obsidian: MaterialMap {
-                        ore: 3,
-                        clay: 14,
-                        ..Default::default()
-                    },
-                    geode: MaterialMap {
-                        ore: 2,
-                        obsidian: 7,
-                        ..Default::default()
-                    },
-                }
+                materials: ["ore", "clay", "obsidian", "geode"]
+                    .map(String::from)
+                    .to_vec(),
+                robot_costs: MaterialMap::from(vec![
+                    MaterialMap::from(vec![4, 0, 0, 0]),
+                    MaterialMap::from(vec![2, 0, 0, 0]),
+                    MaterialMap::from(vec![3, 14, 0, 0]),
+                    MaterialMap::from(vec![2, 0, 7, 0]),
+                ]),
+                target: Material(3),
             }
         );
     }
 
+    #[test]
+    fn it_parses_a_blueprint_with_other_materials() {
+        let blueprint = Blueprint::parse(String::from(BLUEPRINT_WITH_COAL)).unwrap();
+        assert_eq!(blueprint.materials, vec!["ore", "coal", "clay", "diamond"]);
+        assert_eq!(
+            blueprint.robot_costs[&Material(3)],
+            MaterialMap::from(vec![0, 2, 2, 0])
+        );
+        assert_eq!(blueprint.target, Material(3));
+
+        let blueprint = blueprint.with_target("clay").unwrap();
+        assert_eq!(blueprint.target, Material(2));
+        assert!(blueprint.with_target("geode").is_err());
+    }
+
+    #[test]
+    fn it_rejects_a_cost_in_an_unknown_material() {
+        let line = "Blueprint 1: Each ore robot costs 2 ore. Each clay robot costs 3 gold.";
+        assert!(Blueprint::parse(String::from(line)).is_err());
+    }
+
+    #[test]
+    fn it_finds_the_best_plan_for_other_materials() {
+        let blueprint = Blueprint::parse(String::from(BLUEPRINT_WITH_COAL)).unwrap();
+        let plan = get_best_plan(&blueprint, &12);
+        let expected = check_soln(
+            &blueprint,
+            &12,
+            plan.builds
+                .iter()
+                .map(|(_, m)| ["ore", "coal", "clay", "diamond"][m.0]),
+        );
+        assert_eq!(plan.score, expected);
+        assert!(plan.score > 0);
+    }
+
     #[test]
     fn it_iterates_to_short_soln_1() {
         let soln = check_soln(
             &Blueprint::parse(String::from(BLUEPRINT_1)).unwrap(),
             &24,
             vec![
-                Material::Clay,
-                Material::Clay,
-                Material::Clay,
-                Material::Obsidian,
-                Material::Clay,
-                Material::Obsidian,
-                Material::Geode,
-                Material::Geode,
+                "clay", "clay", "clay", "obsidian", "clay", "obsidian", "geode", "geode",
             ]
             .into_iter(),
         );
@@ -156,28 +287,11 @@ mod tests {
             &Blueprint::parse(String::from(BLUEPRINT_1)).unwrap(),
             &32,
             vec![
-                Material::Ore, // I could build Clay earlier, but it's better to build this.
-                Material::Clay,
-                Material::Clay,
-                Material::Clay,
-                Material::Clay,
-                Material::Clay,
-                Material::Clay,
-                Material::Clay, // I could build Obs earlier, but it's better to build this.
-                Material::Obsidian,
-                Material::Obsidian,
-                Material::Obsidian,
-                Material::Obsidian,
-                Material::Geode,
-                Material::Obsidian,
-                Material::Geode,
-                Material::Geode,
-                Material::Geode,
-                Material::Geode,
-                Material::Geode,
-                Material::Geode,
-                Material::Geode,
-                Material::Geode,
+                "ore", // I could build Clay earlier, but it's better to build this.
+                "clay", "clay", "clay", "clay", "clay", "clay",
+                "clay", // I could build Obs earlier, but it's better to build this.
+                "obsidian", "obsidian", "obsidian", "obsidian", "geode", "obsidian", "geode",
+                "geode", "geode", "geode", "geode", "geode", "geode", "geode",
             ]
             .into_iter(),
         );
@@ -190,22 +304,8 @@ mod tests {
             &Blueprint::parse(String::from(BLUEPRINT_2)).unwrap(),
             &24,
             vec![
-                Material::Ore,
-                Material::Ore,
-                Material::Clay,
-                Material::Clay,
-                Material::Clay,
-                Material::Clay,
-                Material::Clay,
-                Material::Obsidian,
-                Material::Obsidian,
-                Material::Obsidian,
-                Material::Obsidian,
-                Material::Obsidian,
-                Material::Geode,
-                Material::Obsidian,
-                Material::Geode,
-                Material::Geode,
+                "ore", "ore", "clay", "clay", "clay", "clay", "clay", "obsidian", "obsidian",
+                "obsidian", "obsidian", "obsidian", "geode", "obsidian", "geode", "geode",
             ]
             .into_iter(),
         );
@@ -221,39 +321,82 @@ mod tests {
             vec![
                 // This soln has the property that, on each turn, the best robot possible is built.
                 // Nothing built on the first two turns
-                Material::Ore,
+                "ore",
                 // Nothing built on this turn. After this, a robot is built on every other turn.
-                Material::Ore,
-                Material::Clay,
-                Material::Clay,
-                Material::Clay,
-                Material::Clay,
-                Material::Clay,
-                Material::Obsidian,
-                Material::Clay, // Redundant
-                Material::Obsidian,
-                Material::Obsidian,
-                Material::Obsidian,
-                Material::Clay, // Redundant
-                Material::Obsidian,
-                Material::Geode,
-                Material::Obsidian,
-                Material::Geode,
-                Material::Obsidian,
-                Material::Geode,
-                Material::Obsidian,
-                Material::Geode,
-                Material::Obsidian,
-                Material::Geode,
-                Material::Geode,
-                Material::Geode,
-                Material::Obsidian, // Redundant
-                Material::Geode,
-                Material::Geode,
-                Material::Geode, // Redundant
+                "ore", "clay", "clay", "clay", "clay", "clay", "obsidian", "clay", // Redundant
+                "obsidian", "obsidian", "obsidian", "clay", // Redundant
+                "obsidian", "geode", "obsidian", "geode", "obsidian", "geode", "obsidian", "geode",
+                "obsidian", "geode", "geode", "geode", "obsidian", // Redundant
+                "geode", "geode", "geode", // Redundant
             ]
             .into_iter(),
         );
         assert_eq!(soln, 62);
     }
+
+    #[test]
+    fn it_finds_the_best_plan() {
+        let blueprint = Blueprint::parse(String::from(BLUEPRINT_1)).unwrap();
+        let plan = get_best_plan(&blueprint, &24);
+        assert_eq!(plan.score, 9);
+
+        // Replaying the plan should give the same number of geodes
+        let states = plan.replay(&blueprint, &24).unwrap();
+        assert_eq!(states.len(), 24);
+        assert_eq!(states.last().unwrap().score(), 9);
+        assert_eq!(
+            states.iter().filter(|state| state.built.is_some()).count(),
+            plan.builds.len()
+        );
+    }
+
+    #[test]
+    fn it_rejects_an_impossible_plan() {
+        let blueprint = Blueprint::parse(String::from(BLUEPRINT_1)).unwrap();
+        let plan = BuildPlan {
+            score: 0,
+            builds: vec![(2, Material(1))],
+        };
+        assert!(plan.replay(&blueprint, &24).is_err());
+    }
+
+    #[test]
+    fn it_renders_a_plan() {
+        let input = [BLUEPRINT_1].map(String::from).into_iter();
+        let params = Params::parse("small", ["turns=24"].map(String::from).into_iter()).unwrap();
+        let log = solve_plan(input, &params).unwrap();
+        assert!(log.contains("== Minute 3 ==\nBuilt a clay robot\n"));
+        assert!(log.ends_with("Geodes opened: 9\nQuality level: 9"));
+    }
+
+    #[test]
+    fn it_runs_a() {
+        let input = [BLUEPRINT_1, BLUEPRINT_2].map(String::from).into_iter();
+        let result = solve_a(input, &Params::default()).unwrap();
+        assert_eq!(result, 33);
+    }
+
+    #[test]
+    fn it_runs_b() {
+        let input = [BLUEPRINT_1, BLUEPRINT_2].map(String::from).into_iter();
+        let result = solve_b(input, &Params::default()).unwrap();
+        assert_eq!(result, 56 * 62);
+    }
+
+    #[test]
+    fn it_runs_with_a_subset_of_blueprints_and_turns() {
+        let input = [BLUEPRINT_1, BLUEPRINT_2].map(String::from).into_iter();
+        let params = Params::parse(
+            "small",
+            ["blueprints=2", "turns=24"].map(String::from).into_iter(),
+        )
+        .unwrap();
+        let result = solve_a(input, &params).unwrap();
+        assert_eq!(result, 2 * 12);
+
+        let input = [BLUEPRINT_1].map(String::from).into_iter();
+        let params =
+            Params::parse("small", ["blueprints=2"].map(String::from).into_iter()).unwrap();
+        assert!(solve_a(input, &params).is_err());
+    }
 }
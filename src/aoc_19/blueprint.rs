@@ -6,22 +6,31 @@ use super::material::{Material, MaterialMap};
 
 #[derive(PartialEq, Eq, Debug)]
 pub struct Blueprint {
+    /// The material names, in the order their robots were declared.
+    pub materials: Vec<String>,
     pub robot_costs: MaterialMap<MaterialMap<i32>>,
+    /// The material whose total is the score.
+    pub target: Material,
 }
 
 impl Blueprint {
-    fn parse_cost(cost_str: &str) -> Result<MaterialMap<i32>, Box<dyn Error>> {
-        let cost_regex = Regex::new("(?P<number>\\d+) (?P<material>ore|clay|obsidian)")?;
+    fn parse_cost(
+        materials: &[String],
+        cost_str: &str,
+    ) -> Result<MaterialMap<i32>, Box<dyn Error>> {
+        let cost_regex = Regex::new("(?P<number>\\d+) (?P<material>\\w+)")?;
 
-        let mut cost = MaterialMap::default();
+        let mut cost = MaterialMap::new(materials.len());
         for caps in cost_regex.captures_iter(cost_str) {
-            let material = match caps.name("material").and_then(|x| Some(x.as_str())) {
-                Some("ore") => Some(Material::Ore),
-                Some("clay") => Some(Material::Clay),
-                Some("obsidian") => Some(Material::Obsidian),
-                _ => None,
-            }
-            .ok_or("Could not parse material")?;
+            let name = caps
+                .name("material")
+                .ok_or("Could not parse material")?
+                .as_str();
+            let material = materials
+                .iter()
+                .position(|m| m == name)
+                .map(Material)
+                .ok_or(format!("No robot collects {}", name))?;
             let number: i32 = caps
                 .name("number")
                 .ok_or("Could not parse number")?
@@ -33,18 +42,63 @@ impl Blueprint {
         Ok(cost)
     }
 
+    /// Parses a blueprint with any number of robots. The factory starts with a
+    /// single robot of the first material declared, and the score is the
+    /// amount of geode collected (or the last material declared, if there's no
+    /// geode robot).
     pub fn parse(line: String) -> Result<Blueprint, Box<dyn Error>> {
-        let (ore_str, clay_str, obsidian_str, geode_str) = line
-            .split(". ")
-            .collect_tuple()
-            .ok_or("Invalid number of robots")?;
+        let robot_regex = Regex::new("Each (?P<material>\\w+) robot costs (?P<cost>[^.]+)")?;
+
+        let robots = robot_regex
+            .captures_iter(&line)
+            .map(|caps| (caps["material"].to_string(), caps["cost"].to_string()))
+            .collect_vec();
+        if robots.is_empty() {
+            return Err("Blueprint has no robots".into());
+        }
+
+        let materials = robots.iter().map(|(name, _)| name.clone()).collect_vec();
+        let robot_costs = robots
+            .iter()
+            .map(|(_, cost_str)| Self::parse_cost(&materials, cost_str))
+            .collect::<Result<Vec<_>, _>>()?;
+        let target = materials
+            .iter()
+            .position(|m| m == "geode")
+            .unwrap_or(materials.len() - 1);
+
         Ok(Blueprint {
-            robot_costs: MaterialMap {
-                ore: Self::parse_cost(ore_str)?,
-                clay: Self::parse_cost(clay_str)?,
-                obsidian: Self::parse_cost(obsidian_str)?,
-                geode: Self::parse_cost(geode_str)?,
-            },
+            materials,
+            robot_costs: robot_costs.into(),
+            target: Material(target),
         })
     }
+
+    pub fn material(&self, name: &str) -> Option<Material> {
+        self.materials.iter().position(|m| m == name).map(Material)
+    }
+
+    pub fn name(&self, material: &Material) -> &str {
+        &self.materials[material.0]
+    }
+
+    /// The most of each material that could be spent in a single turn. The
+    /// target material is never spent down, so it has no limit.
+    pub fn max_spend_rates(&self) -> MaterialMap<i32> {
+        let mut rates: MaterialMap<i32> = MaterialMap::new(self.materials.len());
+        for (_, costs) in &self.robot_costs {
+            for (material, cost) in costs {
+                rates[&material] = rates[&material].max(*cost);
+            }
+        }
+        rates[&self.target] = i32::MAX;
+        rates
+    }
+
+    pub fn with_target(self, name: &str) -> Result<Blueprint, Box<dyn Error>> {
+        let target = self
+            .material(name)
+            .ok_or(format!("No robot collects {}", name))?;
+        Ok(Blueprint { target, ..self })
+    }
 }
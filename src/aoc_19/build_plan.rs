@@ -0,0 +1,86 @@
+use std::error::Error;
+
+use super::blueprint::Blueprint;
+use super::factory_state::FactoryState;
+use super::material::Material;
+
+/// The order in which to build robots, with the turn at the end of which each
+/// robot is ready.
+#[derive(PartialEq, Eq, Debug, Default, Clone)]
+pub struct BuildPlan {
+    pub score: i32,
+    pub builds: Vec<(usize, Material)>,
+}
+
+impl BuildPlan {
+    pub fn from_states(factory_states: &[FactoryState]) -> Self {
+        BuildPlan {
+            score: factory_states.last().map_or(0, |state| state.score()),
+            builds: factory_states
+                .iter()
+                .filter_map(|state| Some((state.turn, state.built?)))
+                .collect(),
+        }
+    }
+
+    /// Replays the plan, returning the state of the factory at the end of every
+    /// turn.
+    pub fn replay<'a>(
+        &self,
+        blueprint: &'a Blueprint,
+        max_turns: &'a usize,
+    ) -> Result<Vec<FactoryState<'a>>, Box<dyn Error>> {
+        let mut built_states = vec![FactoryState::new(blueprint, max_turns)];
+        for (turn, material) in &self.builds {
+            let next_state = built_states
+                .last()
+                .unwrap()
+                .build_next_robot(material)
+                .ok_or(format!(
+                    "Could not build a {} robot",
+                    blueprint.name(material)
+                ))?;
+            if next_state.turn != *turn {
+                return Err(format!(
+                    "{} robot was ready at turn {}, not turn {}",
+                    blueprint.name(material),
+                    next_state.turn,
+                    turn
+                )
+                .into());
+            }
+            built_states.push(next_state);
+        }
+
+        let mut states = Vec::new();
+        let mut built_states = built_states.into_iter().peekable();
+        let mut current = built_states.next().unwrap();
+        for turn in 1..=*max_turns {
+            if built_states.peek().map(|state| state.turn) == Some(turn) {
+                current = built_states.next().unwrap();
+                states.push(current.clone());
+            } else {
+                states.push(current.advance(turn - current.turn));
+            }
+        }
+        Ok(states)
+    }
+
+    /// Renders a turn-by-turn log of the factory when following this plan.
+    pub fn render(
+        &self,
+        blueprint: &Blueprint,
+        max_turns: &usize,
+    ) -> Result<String, Box<dyn Error>> {
+        let mut log = String::new();
+        for state in self.replay(blueprint, max_turns)? {
+            log.push_str(&format!("\n== Minute {} ==\n", state.turn));
+            if let Some(material) = &state.built {
+                log.push_str(&format!("Built a {} robot\n", blueprint.name(material)));
+            }
+            log.push_str(&format!("{}", state));
+        }
+        log.push_str(&format!("\nGeodes opened: {}\n", self.score));
+        Ok(log)
+    }
+}
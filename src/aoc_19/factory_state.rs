@@ -6,6 +6,8 @@ use super::material::{Material, MaterialMap};
 #[derive(Clone)]
 pub struct FactoryState<'a> {
     pub turn: usize,
+    /// The robot which was built at the end of this turn, if any.
+    pub built: Option<Material>,
     max_turns: &'a usize,
     blueprint: &'a Blueprint,
     robots: MaterialMap<i32>,
@@ -19,11 +21,19 @@ impl Display for FactoryState<'_> {
         str_.push_str(&format!("Resources:\n"));
 
         for (material, count) in &self.resources {
-            str_.push_str(&format!("  {:?}: {}\n", material, count));
+            str_.push_str(&format!(
+                "  {}: {}\n",
+                self.blueprint.name(&material),
+                count
+            ));
         }
         str_.push_str(&format!("Robots:\n"));
         for (robot_material, robot_count) in &self.robots {
-            str_.push_str(&format!("  {:?}: {}\n", robot_material, robot_count));
+            str_.push_str(&format!(
+                "  {}: {}\n",
+                self.blueprint.name(&robot_material),
+                robot_count
+            ));
         }
 
         write!(f, "{}", &str_)
@@ -31,21 +41,46 @@ impl Display for FactoryState<'_> {
 }
 
 impl FactoryState<'_> {
+    /// The factory starts with a single robot collecting the first material.
     pub fn new<'a>(blueprint: &'a Blueprint, max_turns: &'a usize) -> FactoryState<'a> {
+        let mut robots = MaterialMap::new(blueprint.materials.len());
+        robots[&Material(0)] = 1;
         FactoryState {
             max_turns,
             blueprint,
             turn: 0,
-            robots: MaterialMap {
-                ore: 1,
-                ..Default::default()
-            },
-            resources: MaterialMap {
-                ..Default::default()
-            },
+            built: None,
+            robots,
+            resources: MaterialMap::new(blueprint.materials.len()),
         }
     }
 
+    pub fn each_material(&self) -> impl Iterator<Item = Material> {
+        self.robots.each()
+    }
+
+    pub fn robot_count(&self, material: &Material) -> i32 {
+        self.robots[material]
+    }
+
+    /// Identifies states which will lead to the same outcomes. Any stock of a
+    /// material beyond what could possibly be spent in the remaining turns
+    /// makes no difference, so it's capped.
+    pub fn cache_key(
+        &self,
+        max_spend_rates: &MaterialMap<i32>,
+    ) -> (usize, MaterialMap<i32>, MaterialMap<i32>) {
+        let remaining_turns = (*self.max_turns - self.turn) as i32;
+        let mut resources = self.resources.clone();
+        for (material, rate) in max_spend_rates {
+            if material != self.blueprint.target {
+                resources[&material] =
+                    resources[&material].min(rate.saturating_mul(remaining_turns));
+            }
+        }
+        (self.turn, self.robots.clone(), resources)
+    }
+
     /// Attempts to build this robot.
     ///
     /// Returns a factory state with the new robot if it's possible to build it
@@ -57,7 +92,7 @@ impl FactoryState<'_> {
                 continue;
             }
             let required_turns_for_resource: usize;
-            let missing_resources = cost_amount - &self.resources[&cost_material];
+            let missing_resources = cost_amount - self.resources[&cost_material];
             let production_rate = self.robots[&cost_material];
             if production_rate == 0 {
                 // There will never be enough resources to build this robot
@@ -81,51 +116,72 @@ impl FactoryState<'_> {
             return None;
         }
 
-        let mut new_factory = self.clone();
-        for (robot_material, robot_count) in &new_factory.robots {
-            let material_cost = new_factory.blueprint.robot_costs[robot][&robot_material];
-
-            new_factory.resources[&robot_material] +=
-                robot_count * required_turns as i32 - material_cost;
+        let mut new_factory = self.advance(required_turns);
+        for (cost_material, cost_amount) in &self.blueprint.robot_costs[robot] {
+            new_factory.resources[&cost_material] -= cost_amount;
         }
-        new_factory.turn += required_turns;
         new_factory.robots[robot] += 1;
+        new_factory.built = Some(*robot);
         Some(new_factory)
     }
 
-    /// Runs a simplified iteration, ignoring the costs of ore and clay, and just
-    /// attempting to build Geode robots if possible and Obsidian bots if not for
-    /// the remainder of the iteration.
-    ///
-    /// This gives a (not-necessarily-achievable) upper bound on the possible
-    /// solutions starting from this state.
+    /// Lets the robots collect resources for some turns without building
+    /// anything.
+    pub fn advance(&self, turns: usize) -> Self {
+        let mut new_factory = self.clone();
+        for (robot_material, robot_count) in &self.robots {
+            new_factory.resources[&robot_material] += robot_count * turns as i32;
+        }
+        new_factory.turn += turns;
+        new_factory.built = None;
+        new_factory
+    }
+
+    /// Runs a relaxed version of the remaining turns, where each type of robot
+    /// has its own separate stockpile of resources, and a robot of every type
+    /// can be built on the same turn as soon as its own stockpile can afford
+    /// it.
     ///
-    /// Thanks, @Crazytieguy !
-    pub fn upper_bound_ignoring_ore_and_clay(&self) -> i32 {
-        if &self.turn == self.max_turns {
-            return self.resources[&Material::Geode] + self.robots[&Material::Geode];
-        };
-        let mut state = self.clone();
-        while &state.turn < state.max_turns {
-            if state.resources[&Material::Obsidian]
-                >= state.blueprint.robot_costs[&Material::Geode][&Material::Obsidian]
-            {
-                state.resources[&Material::Obsidian] += state.robots[&Material::Obsidian]
-                    - state.blueprint.robot_costs[&Material::Geode][&Material::Obsidian];
-                state.resources[&Material::Geode] += state.robots[&Material::Geode];
-                state.robots[&Material::Geode] += 1;
-            } else {
-                state.resources[&Material::Obsidian] += state.robots[&Material::Obsidian];
-                state.resources[&Material::Geode] += state.robots[&Material::Geode];
-                state.robots[&Material::Obsidian] += 1;
+    /// No robot can be built any earlier than this in a real plan, whatever the
+    /// costs are, so this gives a (not-necessarily-achievable) upper bound on
+    /// the possible solutions starting from this state.
+    pub fn upper_bound(&self) -> i32 {
+        let target = &self.blueprint.target;
+        let mut robots = self.robots.clone();
+        let mut stockpiles = MaterialMap::from(vec![self.resources.clone(); robots.len()]);
+        let mut total = self.resources[target];
+
+        for _ in self.turn..*self.max_turns {
+            let mut built = Vec::new();
+            for robot in robots.each() {
+                let costs = &self.blueprint.robot_costs[&robot];
+                let stockpile = &mut stockpiles[&robot];
+                if costs
+                    .into_iter()
+                    .all(|(material, cost)| stockpile[&material] >= *cost)
+                {
+                    for (material, cost) in costs {
+                        stockpile[&material] -= cost;
+                    }
+                    built.push(robot);
+                }
+            }
+            for stockpile in stockpiles.each() {
+                for (material, count) in &robots {
+                    stockpiles[&stockpile][&material] += count;
+                }
+            }
+            total += robots[target];
+            for robot in built {
+                robots[&robot] += 1;
             }
-            state.turn += 1;
         }
-        return state.resources[&Material::Geode] + self.robots[&Material::Geode];
+        total
     }
 
     pub fn score(&self) -> i32 {
+        let target = &self.blueprint.target;
         let remaining_turns = *self.max_turns as i32 - self.turn as i32;
-        self.resources[&Material::Geode] + self.robots[&Material::Geode] * remaining_turns as i32
+        self.resources[target] + self.robots[target] * remaining_turns
     }
 }
@@ -1,115 +1,70 @@
-use std::{
-    fmt::Display,
-    ops::{Index, IndexMut},
-};
+use std::ops::{Index, IndexMut};
 
-#[derive(PartialEq, Eq, Debug, Hash, Clone)]
-pub enum Material {
-    Ore,
-    Clay,
-    Obsidian,
-    Geode,
-}
+/// A material, identified by the order in which its robot was declared in the
+/// blueprint. The names of the materials are kept in the blueprint.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy)]
+pub struct Material(pub usize);
 
-static MATERIALS: [Material; 4] = [
-    Material::Ore,
-    Material::Clay,
-    Material::Obsidian,
-    Material::Geode,
-];
+/// A value for each material in a blueprint, indexed by material.
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+pub struct MaterialMap<T>(Vec<T>);
 
-impl Material {
-    pub fn each() -> &'static [Material; 4] {
-        &MATERIALS
+impl<T: Default + Clone> MaterialMap<T> {
+    /// Creates a map with a default value for each of the given number of
+    /// materials.
+    pub fn new(material_count: usize) -> Self {
+        MaterialMap(vec![Default::default(); material_count])
     }
 }
 
-impl Display for Material {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Material::Ore => "Ore",
-                Material::Clay => "Clay",
-                Material::Obsidian => "Obsidian",
-                Material::Geode => "Geode",
-            }
-        )
+impl<T> MaterialMap<T> {
+    pub fn len(&self) -> usize {
+        self.0.len()
     }
-}
 
-#[derive(PartialEq, Eq, Debug, Clone)]
-pub struct MaterialMap<T>
-where
-    T: Default,
-{
-    pub ore: T,
-    pub clay: T,
-    pub obsidian: T,
-    pub geode: T,
+    pub fn each(&self) -> impl Iterator<Item = Material> {
+        (0..self.0.len()).map(Material)
+    }
 }
 
-impl<T: Default> Default for MaterialMap<T> {
-    fn default() -> Self {
-        MaterialMap {
-            ore: Default::default(),
-            clay: Default::default(),
-            obsidian: Default::default(),
-            geode: Default::default(),
-        }
+impl<T> From<Vec<T>> for MaterialMap<T> {
+    fn from(values: Vec<T>) -> Self {
+        MaterialMap(values)
     }
 }
 
-impl<T: Default> Index<&Material> for MaterialMap<T> {
+impl<T> Index<&Material> for MaterialMap<T> {
     type Output = T;
 
     fn index(&self, index: &Material) -> &Self::Output {
-        match index {
-            Material::Ore => &self.ore,
-            Material::Clay => &self.clay,
-            Material::Obsidian => &self.obsidian,
-            Material::Geode => &self.geode,
-        }
+        &self.0[index.0]
     }
 }
 
-impl<T: Default> IndexMut<&Material> for MaterialMap<T> {
+impl<T> IndexMut<&Material> for MaterialMap<T> {
     fn index_mut(&mut self, index: &Material) -> &mut Self::Output {
-        match index {
-            Material::Ore => &mut self.ore,
-            Material::Clay => &mut self.clay,
-            Material::Obsidian => &mut self.obsidian,
-            Material::Geode => &mut self.geode,
-        }
+        &mut self.0[index.0]
     }
 }
 
-// This doesn't appear to be any slower than using MATERIALS and iterating over
-// the key/value pairs.
-pub struct MaterialMapIterator<'a, T: Default> {
+pub struct MaterialMapIterator<'a, T> {
     material_map: &'a MaterialMap<T>,
     idx: usize,
 }
 
-impl<'a, T: Default> Iterator for MaterialMapIterator<'a, T> {
-    type Item = (&'static Material, &'a T);
+impl<'a, T> Iterator for MaterialMapIterator<'a, T> {
+    type Item = (Material, &'a T);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let result = match self.idx {
-            0 => Some((&MATERIALS[0], &self.material_map.ore)),
-            1 => Some((&MATERIALS[1], &self.material_map.clay)),
-            2 => Some((&MATERIALS[2], &self.material_map.obsidian)),
-            3 => Some((&MATERIALS[3], &self.material_map.geode)),
-            _ => None,
-        };
+        let value = self.material_map.0.get(self.idx)?;
+        let result = (Material(self.idx), value);
         self.idx += 1;
-        result
+        Some(result)
     }
 }
 
-impl<'a, T: Default> IntoIterator for &'a MaterialMap<T> {
-    type Item = (&'static Material, &'a T);
+impl<'a, T> IntoIterator for &'a MaterialMap<T> {
+    type Item = (Material, &'a T);
     type IntoIter = MaterialMapIterator<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
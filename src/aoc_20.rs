@@ -2,197 +2,259 @@ use std::error::Error;
 
 use itertools::Itertools;
 
-fn shift(numbers: &mut Vec<(usize, i64)>, og_idx: usize) {
-    let l = numbers.len() - 1;
-    // println!("{:?}", &numbers.iter().map(|(_, x)| x).join(", "));
-    let (idx, _) = numbers.iter().find_position(|x| x.0 == og_idx).unwrap();
-    let number = numbers[idx].1;
-    let positive = number > 0;
-
-    let mut idx = idx;
-    for _ in 0..(number.abs() % i64::try_from(l).unwrap()) {
-        if positive {
-            if idx < l {
-                numbers.swap(idx, idx + 1);
-                idx += 1;
-            } else {
-                numbers.swap(0, l);
-                idx = 0;
-            }
-        } else {
-            if idx > 0 {
-                numbers.swap(idx - 1, idx);
-                idx -= 1;
-            } else {
-                numbers.swap(0, l);
-                idx = l;
-            }
-        }
-    }
-}
+use crate::params::Params;
+
+mod mixing_list;
+
+use self::mixing_list::MixingList;
 
-pub fn parse(input: impl Iterator<Item = String>) -> Vec<(usize, i64)> {
-    input
-        .enumerate()
-        .map(|(idx, x)| (idx, x.parse::<i64>().unwrap()))
-        .collect_vec()
+pub fn parse(input: impl Iterator<Item = String>) -> Result<Vec<i64>, Box<dyn Error>> {
+    Ok(input
+        .map(|x| x.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()?)
 }
 
-pub fn solve_a(input: impl Iterator<Item = String>) -> Result<i64, Box<dyn Error>> {
-    let mut numbers = parse(input);
-    let l = numbers.len();
+const DECRYPTION_KEY: i64 = 811589153;
+
+/// How to decrypt the numbers and find the grove coordinates.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MixingOptions {
+    /// Each number is multiplied by this before mixing.
+    pub key: i64,
+    pub rounds: usize,
+    /// The grove coordinates are found relative to the first occurrence of
+    /// this value.
+    pub anchor: i64,
+    pub offsets: Vec<usize>,
+    /// Prints the full sequence after each round of mixing.
+    pub dump: bool,
+}
 
-    for og_idx in 0..l {
-        shift(&mut numbers, og_idx);
+impl MixingOptions {
+    pub fn part_a() -> Self {
+        MixingOptions {
+            key: 1,
+            rounds: 1,
+            anchor: 0,
+            offsets: vec![1000, 2000, 3000],
+            dump: false,
+        }
     }
 
-    let (start_idx, _) = numbers.iter().find_position(|x| x.1 == 0).unwrap();
-    let n1 = numbers[(start_idx + 1000) % l].1;
-    let n2 = numbers[(start_idx + 2000) % l].1;
-    let n3 = numbers[(start_idx + 3000) % l].1;
+    pub fn part_b() -> Self {
+        MixingOptions {
+            key: DECRYPTION_KEY,
+            rounds: 10,
+            ..Self::part_a()
+        }
+    }
 
-    println!("{} {} {}", n1, n2, n3);
-    Ok(n1 + n2 + n3)
+    /// Overrides the defaults with any of the params `key`, `rounds`,
+    /// `anchor`, `offsets` (comma-separated) and `dump`.
+    pub fn with_params(self, params: &Params) -> Result<Self, Box<dyn Error>> {
+        Ok(MixingOptions {
+            key: params.get_or("key", self.key)?,
+            rounds: params.get_or("rounds", self.rounds)?,
+            anchor: params.get_or("anchor", self.anchor)?,
+            offsets: params.get_list_or("offsets", self.offsets)?,
+            dump: params.get_or("dump", self.dump)?,
+        })
+    }
 }
 
-const DECRYPTION_KEY: i64 = 811589153;
+fn grove_coordinates(numbers: &MixingList, options: &MixingOptions) -> Result<i64, Box<dyn Error>> {
+    let coordinates = options
+        .offsets
+        .iter()
+        .map(|offset| {
+            numbers
+                .value_after(options.anchor, *offset)
+                .ok_or(format!("No {} in the list", options.anchor))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    println!("{}", coordinates.iter().join(" "));
+    Ok(coordinates.into_iter().sum())
+}
 
-pub fn solve_b(input: impl Iterator<Item = String>) -> Result<i64, Box<dyn Error>> {
-    let mut numbers = parse(input)
+pub fn solve(
+    input: impl Iterator<Item = String>,
+    options: &MixingOptions,
+) -> Result<i64, Box<dyn Error>> {
+    let values = parse(input)?
         .into_iter()
-        .map(|(idx, value)| (idx, value * DECRYPTION_KEY))
-        .collect_vec();
-    let l = numbers.len();
+        .map(|value| {
+            value
+                .checked_mul(options.key)
+                .ok_or("Decrypted value overflows")
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut numbers = MixingList::new(values);
 
-    for _ in 0..10 {
-        for og_idx in 0..l {
-            shift(&mut numbers, og_idx);
+    if options.dump {
+        println!(
+            "Initial arrangement:\n{}\n",
+            numbers.values().iter().join(", ")
+        );
+    }
+    for round in 1..=options.rounds {
+        numbers.mix();
+        if options.dump {
+            println!(
+                "After {} round{} of mixing:\n{}\n",
+                round,
+                if round == 1 { "" } else { "s" },
+                numbers.values().iter().join(", ")
+            );
         }
     }
 
-    let (start_idx, _) = numbers.iter().find_position(|x| x.1 == 0).unwrap();
-    let n1 = numbers[(start_idx + 1000) % l].1;
-    let n2 = numbers[(start_idx + 2000) % l].1;
-    let n3 = numbers[(start_idx + 3000) % l].1;
+    grove_coordinates(&numbers, options)
+}
 
-    println!("{} {} {}", n1, n2, n3);
-    Ok(n1 + n2 + n3)
+pub fn solve_a(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<i64, Box<dyn Error>> {
+    solve(input, &MixingOptions::part_a().with_params(params)?)
+}
+
+pub fn solve_b(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<i64, Box<dyn Error>> {
+    solve(input, &MixingOptions::part_b().with_params(params)?)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// The mixed list is circular, so only the order matters and not which
+    /// number happens to be first.
+    fn assert_same_cycle(actual: Vec<i64>, expected: Vec<i64>) {
+        let (start, _) = actual
+            .iter()
+            .find_position(|x| **x == expected[0])
+            .expect("Expected number should be in the list");
+        let rotated = actual[start..]
+            .iter()
+            .chain(actual[..start].iter())
+            .copied()
+            .collect_vec();
+        assert_eq!(rotated, expected);
+    }
+
     #[test]
     fn it_shifts_forward() {
-        let mut numbers = parse(
-            ["1", "2", "-3", "3", "-2", "0", "4"]
-                .map(String::from)
-                .into_iter(),
-        );
-        shift(&mut numbers, 3);
-        assert_eq!(
-            numbers.into_iter().map(|(_, x)| x).collect_vec(),
-            vec![1, 2, -3, -2, 0, 4, 3]
+        let mut numbers = MixingList::new(
+            parse(
+                ["1", "2", "-3", "3", "-2", "0", "4"]
+                    .map(String::from)
+                    .into_iter(),
+            )
+            .unwrap(),
         );
+        numbers.shift(3);
+        assert_same_cycle(numbers.values(), vec![1, 2, -3, -2, 0, 4, 3]);
     }
 
     #[test]
     fn it_shifts_backward() {
-        let mut numbers = parse(
-            ["1", "2", "-3", "3", "-2", "0", "4"]
-                .map(String::from)
-                .into_iter(),
-        );
-        shift(&mut numbers, 4);
-        assert_eq!(
-            numbers.into_iter().map(|(_, x)| x).collect_vec(),
-            vec![1, 2, -2, -3, 3, 0, 4]
+        let mut numbers = MixingList::new(
+            parse(
+                ["1", "2", "-3", "3", "-2", "0", "4"]
+                    .map(String::from)
+                    .into_iter(),
+            )
+            .unwrap(),
         );
+        numbers.shift(4);
+        assert_same_cycle(numbers.values(), vec![1, 2, -2, -3, 3, 0, 4]);
     }
 
     #[test]
     fn it_shifts_to_back_to_start() {
-        let mut numbers = parse(
-            ["1", "2", "-2", "3", "-7", "0", "4"]
-                .map(String::from)
-                .into_iter(),
-        );
-        shift(&mut numbers, 2);
-        assert_eq!(
-            numbers.into_iter().map(|(_, x)| x).collect_vec(),
-            vec![-2, 1, 2, 3, -7, 0, 4]
+        let mut numbers = MixingList::new(
+            parse(
+                ["1", "2", "-2", "3", "-7", "0", "4"]
+                    .map(String::from)
+                    .into_iter(),
+            )
+            .unwrap(),
         );
+        numbers.shift(2);
+        assert_same_cycle(numbers.values(), vec![-2, 1, 2, 3, -7, 0, 4]);
     }
 
     #[test]
     fn it_shifts_to_end() {
-        let mut numbers = parse(
-            ["1", "2", "-4", "3", "-2", "0", "4"]
-                .map(String::from)
-                .into_iter(),
-        );
-        shift(&mut numbers, 3);
-        assert_eq!(
-            numbers.into_iter().map(|(_, x)| x).collect_vec(),
-            vec![1, 2, -4, -2, 0, 4, 3,]
+        let mut numbers = MixingList::new(
+            parse(
+                ["1", "2", "-4", "3", "-2", "0", "4"]
+                    .map(String::from)
+                    .into_iter(),
+            )
+            .unwrap(),
         );
+        numbers.shift(3);
+        assert_same_cycle(numbers.values(), vec![1, 2, -4, -2, 0, 4, 3]);
     }
 
     #[test]
     fn it_shifts_around_end() {
-        let mut numbers = parse(
-            ["1", "2", "-4", "4", "-2", "0", "6"]
-                .map(String::from)
-                .into_iter(),
-        );
-        shift(&mut numbers, 3);
-        assert_eq!(
-            numbers.into_iter().map(|(_, x)| x).collect_vec(),
-            vec![4, 2, -4, -2, 0, 6, 1]
+        let mut numbers = MixingList::new(
+            parse(
+                ["1", "2", "-4", "4", "-2", "0", "6"]
+                    .map(String::from)
+                    .into_iter(),
+            )
+            .unwrap(),
         );
+        numbers.shift(3);
+        assert_same_cycle(numbers.values(), vec![4, 2, -4, -2, 0, 6, 1]);
     }
 
     #[test]
     fn it_shifts_around_end_more() {
-        let mut numbers = parse(
-            ["1", "2", "-4", "5", "-2", "0", "6"]
-                .map(String::from)
-                .into_iter(),
-        );
-        shift(&mut numbers, 3);
-        assert_eq!(
-            numbers.into_iter().map(|(_, x)| x).collect_vec(),
-            vec![2, 5, -4, -2, 0, 6, 1]
+        let mut numbers = MixingList::new(
+            parse(
+                ["1", "2", "-4", "5", "-2", "0", "6"]
+                    .map(String::from)
+                    .into_iter(),
+            )
+            .unwrap(),
         );
+        numbers.shift(3);
+        assert_same_cycle(numbers.values(), vec![2, 5, -4, -2, 0, 6, 1]);
     }
 
     #[test]
     fn it_shifts_forward_back_to_start() {
-        let mut numbers = parse(
-            ["1", "2", "-4", "5", "-2", "0", "6"]
-                .map(String::from)
-                .into_iter(),
-        );
-        shift(&mut numbers, 6);
-        assert_eq!(
-            numbers.into_iter().map(|(_, x)| x).collect_vec(),
-            vec![1, 2, -4, 5, -2, 0, 6]
+        let mut numbers = MixingList::new(
+            parse(
+                ["1", "2", "-4", "5", "-2", "0", "6"]
+                    .map(String::from)
+                    .into_iter(),
+            )
+            .unwrap(),
         );
+        numbers.shift(6);
+        assert_same_cycle(numbers.values(), vec![1, 2, -4, 5, -2, 0, 6]);
     }
 
     #[test]
     fn it_shifts_backwards_back_to_start() {
-        let mut numbers = parse(
-            ["1", "2", "-4", "-6", "-2", "0", "6"]
-                .map(String::from)
-                .into_iter(),
-        );
-        shift(&mut numbers, 6);
-        assert_eq!(
-            numbers.into_iter().map(|(_, x)| x).collect_vec(),
-            vec![1, 2, -4, -6, -2, 0, 6]
+        let mut numbers = MixingList::new(
+            parse(
+                ["1", "2", "-4", "-6", "-2", "0", "6"]
+                    .map(String::from)
+                    .into_iter(),
+            )
+            .unwrap(),
         );
+        numbers.shift(6);
+        assert_same_cycle(numbers.values(), vec![1, 2, -4, -6, -2, 0, 6]);
     }
 
     #[test]
@@ -200,7 +262,7 @@ mod tests {
         let input = ["1", "2", "-3", "3", "-2", "0", "4"]
             .map(String::from)
             .into_iter();
-        let result = solve_a(input).unwrap();
+        let result = solve_a(input, &Params::default()).unwrap();
         assert_eq!(result, 3);
     }
 
@@ -209,7 +271,41 @@ mod tests {
         let input = ["1", "2", "-3", "3", "-2", "0", "4"]
             .map(String::from)
             .into_iter();
-        let result = solve_b(input).unwrap();
+        let result = solve_b(input, &Params::default()).unwrap();
         assert_eq!(result, 1623178306);
     }
+
+    #[test]
+    fn it_overrides_options_with_params() {
+        let args = ["rounds=3", "key=2", "anchor=4", "offsets=1,2", "dump=true"]
+            .map(String::from)
+            .into_iter();
+        let params = Params::parse("small", args).unwrap();
+        let options = MixingOptions::part_b().with_params(&params).unwrap();
+        assert_eq!(
+            options,
+            MixingOptions {
+                key: 2,
+                rounds: 3,
+                anchor: 4,
+                offsets: vec![1, 2],
+                dump: true,
+            }
+        );
+    }
+
+    #[test]
+    fn it_runs_with_custom_options() {
+        let input = ["1", "2", "-3", "3", "-2", "0", "4"]
+            .map(String::from)
+            .into_iter();
+        // After one round: 1, 2, -3, 4, 0, 3, -2, so 1 and 3 after the 4 are 0 and -2
+        let options = MixingOptions {
+            anchor: 4,
+            offsets: vec![1, 3],
+            ..MixingOptions::part_a()
+        };
+        let result = solve(input, &options).unwrap();
+        assert_eq!(result, -2);
+    }
 }
@@ -0,0 +1,165 @@
+use itertools::Itertools;
+
+/// A circular list of numbers which can be mixed, stored as a list of blocks
+/// of roughly `sqrt(n)` numbers each.
+///
+/// Finding where a number currently is and moving it to a new position are
+/// both O(sqrt(n)): the block containing each number is tracked, so only the
+/// block lengths before it and the block itself need to be scanned. The blocks
+/// drift in size as numbers are moved around, so they're rebuilt every
+/// `block_size` moves.
+pub struct MixingList {
+    values: Vec<i64>,
+    blocks: Vec<Vec<usize>>,
+    block_of: Vec<usize>,
+    block_size: usize,
+    moves_since_rebuild: usize,
+}
+
+impl MixingList {
+    pub fn new(values: Vec<i64>) -> Self {
+        let block_size = (values.len() as f64).sqrt().ceil().max(1.0) as usize;
+        let mut list = MixingList {
+            block_of: vec![0; values.len()],
+            blocks: Vec::new(),
+            values,
+            block_size,
+            moves_since_rebuild: 0,
+        };
+        list.rebuild((0..list.values.len()).collect_vec());
+        list
+    }
+
+    fn rebuild(&mut self, order: Vec<usize>) {
+        self.blocks = order
+            .chunks(self.block_size)
+            .map(|chunk| chunk.to_vec())
+            .collect_vec();
+        for (block_idx, block) in self.blocks.iter().enumerate() {
+            for id in block {
+                self.block_of[*id] = block_idx;
+            }
+        }
+        self.moves_since_rebuild = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// The ids of the numbers (i.e. their positions in the original input) in
+    /// their current order.
+    fn ids(&self) -> impl Iterator<Item = &usize> {
+        self.blocks.iter().flatten()
+    }
+
+    /// The numbers in their current order.
+    pub fn values(&self) -> Vec<i64> {
+        self.ids().map(|id| self.values[*id]).collect_vec()
+    }
+
+    fn position(&self, id: usize) -> usize {
+        let block_idx = self.block_of[id];
+        let preceding: usize = self.blocks[..block_idx].iter().map(|b| b.len()).sum();
+        let offset = self.blocks[block_idx]
+            .iter()
+            .position(|x| *x == id)
+            .expect("Number should be in its block");
+        preceding + offset
+    }
+
+    fn remove(&mut self, id: usize) -> usize {
+        let idx = self.position(id);
+        let block = &mut self.blocks[self.block_of[id]];
+        let offset = block.iter().position(|x| *x == id).unwrap();
+        block.remove(offset);
+        idx
+    }
+
+    fn insert(&mut self, idx: usize, id: usize) {
+        let mut remaining = idx;
+        for (block_idx, block) in self.blocks.iter_mut().enumerate() {
+            if remaining <= block.len() {
+                block.insert(remaining, id);
+                self.block_of[id] = block_idx;
+                return;
+            }
+            remaining -= block.len();
+        }
+        panic!("Index out of range");
+    }
+
+    /// Moves the number that started at position `id` forwards or backwards by
+    /// its own value.
+    pub fn shift(&mut self, id: usize) {
+        let others = self.len() as i64 - 1;
+        if others <= 0 {
+            return;
+        }
+        let idx = self.remove(id);
+        let new_idx = (idx as i64 + self.values[id]).rem_euclid(others);
+        self.insert(new_idx as usize, id);
+
+        self.moves_since_rebuild += 1;
+        if self.moves_since_rebuild >= self.block_size {
+            let order = self.ids().copied().collect_vec();
+            self.rebuild(order);
+        }
+    }
+
+    /// Moves every number once, in the order they originally appeared.
+    pub fn mix(&mut self) {
+        for id in 0..self.len() {
+            self.shift(id);
+        }
+    }
+
+    /// Returns the value at the given offset after the first occurrence of the
+    /// anchor value, wrapping around the list.
+    pub fn value_after(&self, anchor: i64, offset: usize) -> Option<i64> {
+        let values = self.values();
+        let (anchor_idx, _) = values.iter().find_position(|x| **x == anchor)?;
+        Some(values[(anchor_idx + offset) % values.len()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A straightforward O(n) per move implementation to check against.
+    fn naive_mix(values: &[i64], rounds: usize) -> Vec<i64> {
+        let mut numbers = values.iter().copied().enumerate().collect_vec();
+        let others = values.len() as i64 - 1;
+        for _ in 0..rounds {
+            for id in 0..values.len() {
+                let (idx, _) = numbers.iter().find_position(|x| x.0 == id).unwrap();
+                let number = numbers.remove(idx);
+                let new_idx = (idx as i64 + number.1).rem_euclid(others);
+                numbers.insert(new_idx as usize, number);
+            }
+        }
+        numbers.into_iter().map(|(_, x)| x).collect_vec()
+    }
+
+    #[test]
+    fn it_matches_the_naive_mix() {
+        // A deterministic jumble of large positive and negative numbers
+        let values = (0..2000_i64)
+            .map(|i| (i * 7919 % 2003 - 1000) * 811589153)
+            .collect_vec();
+        let mut list = MixingList::new(values.clone());
+        for _ in 0..3 {
+            list.mix();
+        }
+        assert_eq!(list.values(), naive_mix(&values, 3));
+    }
+
+    #[test]
+    fn it_handles_a_single_number() {
+        let mut list = MixingList::new(vec![5]);
+        list.mix();
+        assert_eq!(list.values(), vec![5]);
+        assert_eq!(list.value_after(5, 1000), Some(5));
+    }
+}
@@ -1,24 +1,41 @@
 use core::panic;
 use std::{
-    cell::RefCell,
     collections::{HashMap, HashSet},
     error::Error,
-    fmt::Debug,
+    fmt::{Debug, Display},
     hash::Hash,
 };
 
 use itertools::Itertools;
 
+use crate::params::Params;
+
+mod expression;
+
+use self::expression::{Equation, Expression};
+
 #[derive(PartialEq, Eq, Copy, Clone, Hash)]
 struct MonkeyName(char, char, char, char);
 
+impl MonkeyName {
+    fn parse(s: &str) -> Result<Self, Box<dyn Error>> {
+        let (a, b, c, d) = s.chars().collect_tuple().ok_or(format!(
+            "Monkey name should be exactly four characters: {}",
+            s
+        ))?;
+        Ok(MonkeyName(a, b, c, d))
+    }
+}
+
 impl From<&str> for MonkeyName {
     fn from(s: &str) -> Self {
-        let (a, b, c, d) = s
-            .chars()
-            .collect_tuple()
-            .expect("Monkey name should be exactly four characters");
-        MonkeyName(a, b, c, d)
+        Self::parse(s).expect("Monkey name should be exactly four characters")
+    }
+}
+
+impl Display for MonkeyName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}{}", self.0, self.1, self.2, self.3)
     }
 }
 
@@ -32,7 +49,7 @@ impl Debug for MonkeyName {
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
 enum Operation {
     Add,
     Subtract,
@@ -40,11 +57,46 @@ enum Operation {
     Divide,
 }
 
+impl Operation {
+    fn apply(&self, a: i64, b: i64) -> Result<i64, Box<dyn Error>> {
+        match self {
+            Operation::Add => a.checked_add(b),
+            Operation::Subtract => a.checked_sub(b),
+            Operation::Multiply => a.checked_mul(b),
+            Operation::Divide => a.checked_div(b),
+        }
+        .ok_or_else(|| format!("Could not evaluate {} {} {}", a, self, b).into())
+    }
+
+    /// Binding strength of the operation when written in infix notation.
+    fn precedence(&self) -> u8 {
+        match self {
+            Operation::Add | Operation::Subtract => 1,
+            Operation::Multiply | Operation::Divide => 2,
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Operation::Add => "+",
+                Operation::Subtract => "-",
+                Operation::Multiply => "*",
+                Operation::Divide => "/",
+            }
+        )
+    }
+}
+
 #[derive(PartialEq, Eq, Debug)]
 struct Monkey {
     name: MonkeyName,
     instruction: Option<(Operation, MonkeyName, MonkeyName)>,
-    call: RefCell<Option<i64>>,
+    call: Option<i64>,
 }
 
 impl Monkey {
@@ -71,18 +123,6 @@ impl Monkey {
         }
         return Some(instruction.parse().expect("Could not parse integer"));
     }
-
-    fn is_resolved(&self) -> bool {
-        *self.call.borrow() != None
-    }
-
-    fn value(&self) -> i64 {
-        self.call.borrow().expect("No value")
-    }
-
-    fn resolve(&self, value: i64) {
-        *self.call.borrow_mut() = Some(value);
-    }
 }
 
 impl From<&str> for Monkey {
@@ -93,7 +133,7 @@ impl From<&str> for Monkey {
         return Monkey {
             name: name.into(),
             instruction,
-            call: RefCell::new(call),
+            call,
         };
     }
 }
@@ -125,40 +165,100 @@ fn parse_monkeys(input: impl Iterator<Item = String>) -> HashMap<MonkeyName, Mon
     monkeys
 }
 
-fn solve_next_monkey(monkeys: &HashMap<MonkeyName, Monkey>) -> bool {
-    for monkey in monkeys.values().filter(|m| !m.is_resolved()) {
-        if let Some((op, monkey_name_1, monkey_name_2)) = &monkey.instruction {
-            let monkey_1 = &monkeys[monkey_name_1];
-            let monkey_2 = &monkeys[monkey_name_2];
-            if monkey_1.is_resolved() && monkey_2.is_resolved() {
-                monkey.resolve(match op {
-                    &Operation::Add => monkey_1.value() + monkey_2.value(),
-                    &Operation::Subtract => monkey_1.value() - monkey_2.value(),
-                    &Operation::Multiply => monkey_1.value() * monkey_2.value(),
-                    &Operation::Divide => monkey_1.value() / monkey_2.value(),
-                });
-                return true;
-            }
+/// Lists the monkeys that the target depends on (including the target itself),
+/// with every monkey appearing after the monkeys it is waiting for. The
+/// dependencies of the unknown monkey (if any) are left out, and it needn't
+/// exist.
+///
+/// This is a depth-first search with an explicit stack, so that long chains of
+/// monkeys don't overflow the call stack. A monkey which is reached again while
+/// it is still waiting on its own dependencies is part of a cycle.
+fn dependency_order(
+    monkeys: &HashMap<MonkeyName, Monkey>,
+    target: MonkeyName,
+    unknown: Option<MonkeyName>,
+) -> Result<Vec<MonkeyName>, Box<dyn Error>> {
+    let mut order = Vec::new();
+    let mut ordered = HashSet::new();
+    let mut waiting = HashSet::new();
+    let mut stack = vec![(target, false)];
+
+    while let Some((name, dependencies_ordered)) = stack.pop() {
+        if dependencies_ordered {
+            waiting.remove(&name);
+            ordered.insert(name);
+            order.push(name);
+            continue;
+        }
+        if ordered.contains(&name) {
+            continue;
+        }
+        if waiting.contains(&name) {
+            return Err(format!("Monkey {} depends on itself", name).into());
+        }
+        if Some(name) == unknown {
+            ordered.insert(name);
+            order.push(name);
+            continue;
+        }
+
+        let monkey = monkeys
+            .get(&name)
+            .ok_or(format!("No monkey named {}", name))?;
+        waiting.insert(name);
+        stack.push((name, true));
+        if let Some((_, m1, m2)) = &monkey.instruction {
+            stack.push((*m2, false));
+            stack.push((*m1, false));
         }
     }
-    false
+
+    Ok(order)
 }
 
-pub fn solve_a(input: impl Iterator<Item = String>) -> Result<i64, Box<dyn Error>> {
-    let monkeys = parse_monkeys(input);
-    while !monkeys[&"root".into()].is_resolved() {
-        if !solve_next_monkey(&monkeys) {
-            panic!("No more monkeys to solve");
+/// Evaluates the monkeys in dependency order. Monkeys which depend on the
+/// unknown monkey (if any) are left out of the returned values.
+fn evaluate(
+    monkeys: &HashMap<MonkeyName, Monkey>,
+    order: &[MonkeyName],
+    unknown: Option<MonkeyName>,
+) -> Result<HashMap<MonkeyName, i64>, Box<dyn Error>> {
+    let mut values = HashMap::new();
+
+    for name in order {
+        if Some(*name) == unknown {
+            continue;
+        }
+        let monkey = &monkeys[name];
+        let value = match &monkey.instruction {
+            Some((op, m1, m2)) => match (values.get(m1), values.get(m2)) {
+                (Some(a), Some(b)) => Some(op.apply(*a, *b)?),
+                _ => None,
+            },
+            None => Some(monkey.call.ok_or(format!("{} has no value", name))?),
         };
+        if let Some(value) = value {
+            values.insert(*name, value);
+        }
     }
-    Ok(monkeys[&"root".into()].value())
+
+    Ok(values)
+}
+
+pub fn solve_a(input: impl Iterator<Item = String>) -> Result<i64, Box<dyn Error>> {
+    let monkeys = parse_monkeys(input);
+    let root = "root".into();
+    let order = dependency_order(&monkeys, root, None)?;
+    let values = evaluate(&monkeys, &order, None)?;
+    Ok(values[&root])
 }
 
 fn invert_monkeys(
     monkeys: &HashMap<MonkeyName, Monkey>,
+    values: &HashMap<MonkeyName, i64>,
     start: MonkeyName,
     end: MonkeyName,
-) -> i64 {
+) -> Result<i64, Box<dyn Error>> {
     let mut monkey_name = start;
     // We represent the start equality as a difference equal to zero, i.e. monkey1 - monkey2 = 0.
     let mut required_value = 0;
@@ -167,28 +267,20 @@ fn invert_monkeys(
         let (op, m1_name, m2_name) = monkeys[&monkey_name]
             .instruction
             .as_ref()
-            .expect("Monkey must have dependencies");
-
-        let m1 = &monkeys[m1_name];
-        let m2 = &monkeys[m2_name];
-
-        let (next_monkey_name, other_monkey_value, unknown_first) = if m1.call.borrow().is_none() {
-            (
-                m1.name,
-                m2.call
-                    .borrow()
-                    .expect("One of the two monkeys must have a value"),
-                true,
-            )
-        } else {
-            (
-                m2.name,
-                m1.call
-                    .borrow()
-                    .expect("One of the two monkeys must have a value"),
-                false,
-            )
-        };
+            .ok_or(format!("Monkey {} must have dependencies", monkey_name))?;
+
+        let (next_monkey_name, other_monkey_value, unknown_first) =
+            match (values.get(m1_name), values.get(m2_name)) {
+                (None, Some(value)) => (*m1_name, *value, true),
+                (Some(value), None) => (*m2_name, *value, false),
+                _ => {
+                    return Err(format!(
+                        "Exactly one of {} and {} should depend on {}",
+                        m1_name, m2_name, end
+                    )
+                    .into())
+                }
+            };
 
         monkey_name = next_monkey_name;
 
@@ -206,7 +298,7 @@ fn invert_monkeys(
                 required_value /= other_monkey_value;
             }
             (&Operation::Divide, true) => {
-                required_value = required_value * other_monkey_value;
+                required_value *= other_monkey_value;
             }
             (&Operation::Divide, false) => {
                 required_value = other_monkey_value / required_value;
@@ -214,46 +306,70 @@ fn invert_monkeys(
         }
     }
 
-    required_value
+    Ok(required_value)
 }
 
 pub fn solve_b(input: impl Iterator<Item = String>) -> Result<i64, Box<dyn Error>> {
     let mut monkeys = parse_monkeys(input);
     check_is_tree(&monkeys);
+    let root = "root".into();
+    let human = "humn".into();
 
-    let mut root_monkey = monkeys
-        .remove(&"root".into())
-        .expect("should be a root monkey");
+    let root_monkey = monkeys.get_mut(&root).ok_or("should be a root monkey")?;
     if let Some((_, m1, m2)) = root_monkey.instruction {
         root_monkey.instruction = Some((Operation::Subtract, m1, m2));
     }
-    monkeys.insert("root".into(), root_monkey);
-
-    let mut human = monkeys.remove(&"humn".into()).expect("should be a human");
-    human.instruction = None;
-    *human.call.borrow_mut() = None;
-    monkeys.insert("humn".into(), human);
 
     // Solves all monkeys which don't have a dependency on `humn`
-    while solve_next_monkey(&monkeys) {}
+    let order = dependency_order(&monkeys, root, None)?;
+    let values = evaluate(&monkeys, &order, Some(human))?;
 
-    let result = invert_monkeys(&monkeys, "root".into(), "humn".into());
+    let result = invert_monkeys(&monkeys, &values, root, human)?;
 
     // Sanity check: solve it again, and make sure that the root value evaluates to zero.
-    let human = monkeys.remove(&"humn".into()).expect("should be a human");
-    *human.call.borrow_mut() = Some(result);
-    monkeys.insert("humn".into(), human);
-
-    while !monkeys[&"root".into()].is_resolved() {
-        if !solve_next_monkey(&monkeys) {
-            panic!("No more monkeys to solve");
-        };
-    }
-    assert_eq!(0, monkeys[&"root".into()].value());
+    let human_monkey = monkeys.get_mut(&human).ok_or("should be a human")?;
+    human_monkey.instruction = None;
+    human_monkey.call = Some(result);
+    let values = evaluate(&monkeys, &order, None)?;
+    assert_eq!(0, values[&root]);
 
     Ok(result)
 }
 
+/// Renders the equation that the root monkey represents, with every monkey
+/// that doesn't depend on the unknown monkey folded into a constant.
+///
+/// Params:
+/// - `root`: the monkey to render (default `root`)
+/// - `unknown`: the monkey to leave as a variable (default `humn`)
+/// - `value`: if given, also evaluates the root monkey with the unknown monkey
+///   yelling this value
+pub fn solve_equation(
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<String, Box<dyn Error>> {
+    let monkeys = parse_monkeys(input);
+    let root = MonkeyName::parse(params.get("root").unwrap_or("root"))?;
+    let unknown = MonkeyName::parse(params.get("unknown").unwrap_or("humn"))?;
+
+    let expression = Expression::build(&monkeys, root, unknown)?;
+    let mut output = format!("{}: {}", root, expression);
+    if let Ok(equation) = Equation::build(&monkeys, root, unknown) {
+        output.push_str(&format!("\nequality: {}", equation));
+    }
+    if params.get("value").is_some() {
+        let value: i64 = params.get_or("value", 0)?;
+        output.push_str(&format!(
+            "\n{} ({} = {}): {}",
+            root,
+            unknown,
+            value,
+            expression.evaluate(value)?
+        ));
+    }
+    Ok(output)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -262,6 +378,7 @@ mod tests {
     fn it_parses_a_monkey_name() {
         let result = MonkeyName::from("root");
         assert_eq!(result, MonkeyName('r', 'o', 'o', 't'));
+        assert!(MonkeyName::parse("hum").is_err());
     }
 
     #[test]
@@ -271,7 +388,7 @@ mod tests {
             Monkey {
                 name: "root".into(),
                 instruction: Some((Operation::Add, "pppw".into(), "sjmn".into())),
-                call: RefCell::new(None),
+                call: None,
             }
         );
         assert_eq!(
@@ -279,7 +396,7 @@ mod tests {
             Monkey {
                 name: "ptdq".into(),
                 instruction: Some((Operation::Subtract, "humn".into(), "dvpt".into())),
-                call: RefCell::new(None),
+                call: None,
             }
         );
         assert_eq!(
@@ -287,7 +404,7 @@ mod tests {
             Monkey {
                 name: "pppw".into(),
                 instruction: Some((Operation::Divide, "cczh".into(), "lfqf".into())),
-                call: RefCell::new(None),
+                call: None,
             }
         );
         assert_eq!(
@@ -295,7 +412,7 @@ mod tests {
             Monkey {
                 name: "lgvd".into(),
                 instruction: Some((Operation::Multiply, "ljgn".into(), "ptdq".into())),
-                call: RefCell::new(None),
+                call: None,
             }
         );
         assert_eq!(
@@ -303,8 +420,133 @@ mod tests {
             Monkey {
                 name: "dbpl".into(),
                 instruction: None,
-                call: RefCell::new(Some(5)),
+                call: Some(5),
             }
         );
     }
+
+    fn example_input() -> impl Iterator<Item = String> {
+        [
+            "root: pppw + sjmn",
+            "dbpl: 5",
+            "cczh: sllz + lgvd",
+            "zczc: 2",
+            "ptdq: humn - dvpt",
+            "dvpt: 3",
+            "lfqf: 4",
+            "humn: 5",
+            "ljgn: 2",
+            "sjmn: drzm * dbpl",
+            "sllz: 4",
+            "pppw: cczh / lfqf",
+            "lgvd: ljgn * ptdq",
+            "drzm: hmdt - zczc",
+            "hmdt: 32",
+        ]
+        .map(String::from)
+        .into_iter()
+    }
+
+    #[test]
+    fn it_runs_a() {
+        let result = solve_a(example_input()).unwrap();
+        assert_eq!(result, 152);
+    }
+
+    #[test]
+    fn it_runs_b() {
+        let result = solve_b(example_input()).unwrap();
+        assert_eq!(result, 301);
+    }
+
+    #[test]
+    fn it_errors_on_a_cycle() {
+        let input = [
+            "root: abcd + efgh",
+            "abcd: efgh * 2222",
+            "efgh: abcd - 1111",
+            "2222: 2",
+            "1111: 1",
+        ]
+        .map(String::from)
+        .into_iter();
+        assert!(solve_a(input).is_err());
+    }
+
+    #[test]
+    fn it_errors_on_a_missing_monkey() {
+        let input = ["root: abcd + efgh", "abcd: 3"]
+            .map(String::from)
+            .into_iter();
+        assert!(solve_a(input).is_err());
+    }
+
+    /// Each monkey adds one to the next, down to a final monkey yelling zero.
+    fn long_chain_input() -> impl Iterator<Item = String> {
+        let names = (0..100_000)
+            .map(|i| {
+                let mut name = String::from("root");
+                if i > 0 {
+                    name = (0..4)
+                        .map(|place| (b'a' + (i / 26_usize.pow(place) % 26) as u8) as char)
+                        .collect();
+                }
+                name
+            })
+            .collect_vec();
+        let mut input = Vec::new();
+        input.push(String::from("zzzz: 1"));
+        for (name, next) in names.iter().tuple_windows() {
+            input.push(format!("{}: {} + zzzz", name, next));
+        }
+        input.push(format!("{}: 0", names[names.len() - 1]));
+        input.into_iter()
+    }
+
+    #[test]
+    fn it_evaluates_a_long_chain_of_monkeys() {
+        let result = solve_a(long_chain_input()).unwrap();
+        assert_eq!(result, 99_999);
+    }
+
+    #[test]
+    fn it_renders_a_long_chain_of_monkeys() {
+        let result = solve_equation(long_chain_input(), &Params::default()).unwrap();
+        assert_eq!(result, "root: 99999\nequality: 99998 = 1");
+    }
+
+    #[test]
+    fn it_renders_a_long_chain_ending_in_the_unknown() {
+        // With the unknown at the bottom of the chain, nothing folds away
+        let bottom = long_chain_input().last().unwrap();
+        let unknown = &bottom[..4];
+        let args = [format!("unknown={}", unknown), "value=0".to_string()];
+        let params = Params::parse("small", args.into_iter()).unwrap();
+        let result = solve_equation(long_chain_input(), &params).unwrap();
+        let lines = result.lines().collect_vec();
+        assert_eq!(
+            lines[0],
+            format!("root: {}{}", unknown, " + 1".repeat(99_999))
+        );
+        assert_eq!(
+            lines[1],
+            format!("equality: {}{} = 1", unknown, " + 1".repeat(99_998))
+        );
+        assert_eq!(lines[2], format!("root ({} = 0): 99999", unknown));
+    }
+
+    #[test]
+    fn it_renders_and_evaluates_the_equation() {
+        let params = Params::parse("small", ["value=5"].map(String::from).into_iter()).unwrap();
+        let result = solve_equation(example_input(), &params).unwrap();
+        assert_eq!(
+            result,
+            [
+                "root: (4 + 2 * (humn - 3)) / 4 + 150",
+                "equality: (4 + 2 * (humn - 3)) / 4 = 150",
+                "root (humn = 5): 152",
+            ]
+            .join("\n")
+        );
+    }
 }
@@ -0,0 +1,276 @@
+use std::{collections::HashMap, error::Error, fmt::Display, mem};
+
+use super::{dependency_order, Monkey, MonkeyName, Operation};
+
+/// The value a monkey yells, written in terms of a single unknown monkey.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Expression {
+    Constant(i64),
+    Unknown(MonkeyName),
+    Apply(Operation, Box<Expression>, Box<Expression>),
+}
+
+impl Expression {
+    /// Builds the expression for the named monkey. Every subtree which doesn't
+    /// depend on the unknown monkey is folded into a constant.
+    ///
+    /// The monkeys are built in dependency order rather than recursively, so
+    /// that long chains of monkeys don't overflow the call stack.
+    pub fn build(
+        monkeys: &HashMap<MonkeyName, Monkey>,
+        name: MonkeyName,
+        unknown: MonkeyName,
+    ) -> Result<Expression, Box<dyn Error>> {
+        let order = dependency_order(monkeys, name, Some(unknown))?;
+
+        // Each expression is moved into the first monkey to use it, as long as
+        // no other monkey uses it too
+        let mut uses: HashMap<MonkeyName, usize> = HashMap::new();
+        for monkey in order.iter().filter(|m| **m != unknown) {
+            if let Some((_, m1, m2)) = &monkeys[monkey].instruction {
+                *uses.entry(*m1).or_default() += 1;
+                *uses.entry(*m2).or_default() += 1;
+            }
+        }
+        let mut expressions: HashMap<MonkeyName, Expression> = HashMap::new();
+        let mut take = |expressions: &mut HashMap<MonkeyName, Expression>, name| {
+            let remaining = uses.get_mut(&name).expect("dependencies are counted");
+            *remaining -= 1;
+            if *remaining == 0 {
+                expressions.remove(&name)
+            } else {
+                expressions.get(&name).cloned()
+            }
+            .expect("dependencies come first")
+        };
+
+        for monkey_name in order {
+            if monkey_name == unknown {
+                expressions.insert(monkey_name, Expression::Unknown(monkey_name));
+                continue;
+            }
+            let monkey = &monkeys[&monkey_name];
+            let expression = match &monkey.instruction {
+                None => Expression::Constant(
+                    monkey.call.ok_or(format!("{} has no value", monkey_name))?,
+                ),
+                Some((op, m1, m2)) => {
+                    let left = take(&mut expressions, *m1);
+                    let right = take(&mut expressions, *m2);
+                    match (left, right) {
+                        (Expression::Constant(a), Expression::Constant(b)) => {
+                            Expression::Constant(op.apply(a, b)?)
+                        }
+                        (left, right) => Expression::Apply(*op, Box::new(left), Box::new(right)),
+                    }
+                }
+            };
+            expressions.insert(monkey_name, expression);
+        }
+        Ok(expressions.remove(&name).expect("the target comes last"))
+    }
+
+    /// Evaluates the expression with the unknown monkey yelling the given value.
+    ///
+    /// Like the rest of the methods that walk the tree, this keeps its own
+    /// stack rather than recursing, as the tree can be as deep as the chain of
+    /// monkeys is long.
+    pub fn evaluate(&self, value: i64) -> Result<i64, Box<dyn Error>> {
+        let mut values = Vec::new();
+        let mut stack = vec![(self, false)];
+        while let Some((expression, operands_evaluated)) = stack.pop() {
+            match expression {
+                Expression::Constant(c) => values.push(*c),
+                Expression::Unknown(_) => values.push(value),
+                Expression::Apply(op, left, right) => {
+                    if operands_evaluated {
+                        let b = values.pop().expect("operands come first");
+                        let a = values.pop().expect("operands come first");
+                        values.push(op.apply(a, b)?);
+                    } else {
+                        stack.push((expression, true));
+                        stack.push((right, false));
+                        stack.push((left, false));
+                    }
+                }
+            }
+        }
+        Ok(values.pop().expect("the expression has a value"))
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expression::Apply(op, _, _) => op.precedence(),
+            _ => u8::MAX,
+        }
+    }
+
+    /// Whether this needs parentheses as a child of a binary operation, where
+    /// leaving them out would change the meaning.
+    fn needs_parens(&self, parent: &Operation, is_right: bool) -> bool {
+        match self {
+            Expression::Apply(op, _, _) => {
+                self.precedence() < parent.precedence()
+                    || (is_right
+                        && self.precedence() == parent.precedence()
+                        && (matches!(parent, Operation::Subtract | Operation::Divide)
+                            || matches!(op, Operation::Subtract | Operation::Divide)))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A part of an expression still to be written.
+enum Piece<'a> {
+    Text(&'static str),
+    Operator(Operation),
+    /// A subexpression, along with its parent operation and whether it is the
+    /// right-hand operand.
+    Operand(&'a Expression, Option<(Operation, bool)>),
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut stack = vec![Piece::Operand(self, None)];
+        while let Some(piece) = stack.pop() {
+            match piece {
+                Piece::Text(text) => write!(f, "{}", text)?,
+                Piece::Operator(op) => write!(f, " {} ", op)?,
+                Piece::Operand(expression, parent) => match expression {
+                    Expression::Constant(c) => write!(f, "{}", c)?,
+                    Expression::Unknown(name) => write!(f, "{}", name)?,
+                    Expression::Apply(op, left, right) => {
+                        let needs_parens = parent.is_some_and(|(parent, is_right)| {
+                            expression.needs_parens(&parent, is_right)
+                        });
+                        if needs_parens {
+                            write!(f, "(")?;
+                            stack.push(Piece::Text(")"));
+                        }
+                        stack.push(Piece::Operand(right, Some((*op, true))));
+                        stack.push(Piece::Operator(*op));
+                        stack.push(Piece::Operand(left, Some((*op, false))));
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Expression {
+    /// Takes the operands apart one node at a time, as dropping the boxes in
+    /// turn would recurse as deep as the tree.
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        let take_operands = |expression: &mut Expression, stack: &mut Vec<Expression>| {
+            if let Expression::Apply(_, left, right) = expression {
+                stack.push(mem::replace(left.as_mut(), Expression::Constant(0)));
+                stack.push(mem::replace(right.as_mut(), Expression::Constant(0)));
+            }
+        };
+        take_operands(self, &mut stack);
+        while let Some(mut expression) = stack.pop() {
+            take_operands(&mut expression, &mut stack);
+        }
+    }
+}
+
+/// The two sides of the root monkey's equality check, as in part B.
+pub struct Equation {
+    pub left: Expression,
+    pub right: Expression,
+}
+
+impl Equation {
+    pub fn build(
+        monkeys: &HashMap<MonkeyName, Monkey>,
+        root: MonkeyName,
+        unknown: MonkeyName,
+    ) -> Result<Equation, Box<dyn Error>> {
+        let (_, m1, m2) = monkeys
+            .get(&root)
+            .and_then(|monkey| monkey.instruction.as_ref())
+            .ok_or(format!("{} should have two dependencies", root))?;
+        Ok(Equation {
+            left: Expression::build(monkeys, *m1, unknown)?,
+            right: Expression::build(monkeys, *m2, unknown)?,
+        })
+    }
+}
+
+impl Display for Equation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.left, self.right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aoc_21::parse_monkeys;
+
+    fn example_monkeys() -> HashMap<MonkeyName, Monkey> {
+        let input = [
+            "root: pppw + sjmn",
+            "dbpl: 5",
+            "cczh: sllz + lgvd",
+            "zczc: 2",
+            "ptdq: humn - dvpt",
+            "dvpt: 3",
+            "lfqf: 4",
+            "humn: 5",
+            "ljgn: 2",
+            "sjmn: drzm * dbpl",
+            "sllz: 4",
+            "pppw: cczh / lfqf",
+            "lgvd: ljgn * ptdq",
+            "drzm: hmdt - zczc",
+            "hmdt: 32",
+        ]
+        .map(String::from)
+        .into_iter();
+        parse_monkeys(input)
+    }
+
+    #[test]
+    fn it_folds_constant_subtrees() {
+        let monkeys = example_monkeys();
+        let expression = Expression::build(&monkeys, "sjmn".into(), "humn".into()).unwrap();
+        assert_eq!(expression, Expression::Constant(150));
+    }
+
+    #[test]
+    fn it_renders_with_a_different_unknown() {
+        let monkeys = example_monkeys();
+        let expression = Expression::build(&monkeys, "root".into(), "ptdq".into()).unwrap();
+        assert_eq!(format!("{}", expression), "(4 + 2 * ptdq) / 4 + 150");
+        assert_eq!(expression.evaluate(2).unwrap(), 152);
+    }
+
+    #[test]
+    fn it_keeps_parentheses_for_right_hand_subtraction() {
+        let expression = Expression::Apply(
+            Operation::Subtract,
+            Box::new(Expression::Constant(10)),
+            Box::new(Expression::Apply(
+                Operation::Subtract,
+                Box::new(Expression::Unknown("humn".into())),
+                Box::new(Expression::Constant(3)),
+            )),
+        );
+        assert_eq!(format!("{}", expression), "10 - (humn - 3)");
+        assert_eq!(expression.evaluate(5).unwrap(), 8);
+    }
+
+    #[test]
+    fn it_errors_on_a_missing_monkey() {
+        let monkeys = parse_monkeys(
+            ["root: abcd + efgh", "abcd: 1"]
+                .map(String::from)
+                .into_iter(),
+        );
+        assert!(Expression::build(&monkeys, "root".into(), "humn".into()).is_err());
+    }
+}
@@ -1,124 +1,167 @@
-use itertools::Itertools;
-use std::{error::Error, fmt::Display};
-
-#[derive(Debug, PartialEq, Eq)]
-enum SnafuDigit {
-    Two,
-    One,
-    Zero,
-    MinusOne,
-    MinusTwo,
-}
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+use std::time::Instant;
 
-use SnafuDigit::*;
-
-impl From<&char> for SnafuDigit {
-    fn from(s: &char) -> Self {
-        match s {
-            '2' => Two,
-            '1' => One,
-            '0' => Zero,
-            '-' => MinusOne,
-            '=' => MinusTwo,
-            _ => panic!("Unrecognised character"),
-        }
-    }
-}
+use crate::params::Params;
 
-impl From<&SnafuDigit> for char {
-    fn from(s: &SnafuDigit) -> Self {
-        match s {
-            Two => '2',
-            One => '1',
-            Zero => '0',
-            MinusOne => '-',
-            MinusTwo => '=',
-        }
+mod balanced;
+
+use balanced::Base;
+
+pub fn solve_a(input: impl Iterator<Item = String>) -> Result<String, Box<dyn Error>> {
+    let base = Base::snafu();
+    let mut total = base.from_i64(0);
+    for line in input {
+        total = total + base.parse(&line)?;
     }
+
+    Ok(total.to_string())
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct SnafuNumber(Vec<SnafuDigit>);
+/// Recorded in place of an answer for a part that isn't run.
+const SKIPPED: &str = "-";
 
-impl Display for SnafuNumber {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let str = String::from_iter(self.0.iter().map(|s| char::from(s)));
-        write!(f, "{}", str)
-    }
+/// How a part of the calendar fared when it was run.
+enum Outcome {
+    Verified,
+    /// There is no recorded answer to check the answer against.
+    Unverified(String),
+    Wrong(String),
+    Failed(String),
 }
 
-impl From<&str> for SnafuNumber {
-    fn from(s: &str) -> Self {
-        Self(s.chars().map(|s| (&s).into()).collect_vec())
+/// Reads the recorded answers, one per line in the form `01 a 70374`. An
+/// answer of `-` marks a part with no answer that can be checked, such as one
+/// which draws its answer rather than returning it.
+fn read_answers(path: &str) -> Result<HashMap<(u32, char), String>, Box<dyn Error>> {
+    let mut answers = HashMap::new();
+    let Ok(text) = fs::read_to_string(path) else {
+        return Ok(answers);
+    };
+    for line in text.lines() {
+        let mut words = line.splitn(3, ' ');
+        let (Some(day), Some(part), Some(answer)) = (words.next(), words.next(), words.next())
+        else {
+            return Err(format!("Could not parse answer: {:?}", line).into());
+        };
+        let part = part.chars().next().ok_or("Missing part")?;
+        answers.insert((day.parse()?, part), answer.trim().to_string());
     }
+    Ok(answers)
 }
 
-impl From<&SnafuNumber> for String {
-    fn from(s: &SnafuNumber) -> Self {
-        String::from_iter(s.0.iter().map(|d| char::from(d)))
+/// Runs a part in a separate process of this program, so that anything the
+/// solver prints is kept out of the report, and a panic only fails that part.
+fn run_part(day: u32, part: char, size: &str) -> Result<String, String> {
+    let program = env::current_exe().map_err(|e| e.to_string())?;
+    let output = Command::new(program)
+        .args([format!("{:02}", day), part.to_string(), size.to_string()])
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        // The panic message follows the line saying where it happened
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let mut lines = stderr
+            .lines()
+            .skip_while(|line| !line.contains("panicked at"));
+        let message = lines.nth(1).unwrap_or("Exited with an error");
+        return Err(message.to_string());
     }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let prefix = format!("{} soln: ", part);
+    stdout
+        .lines()
+        .rev()
+        .find_map(|line| line.strip_prefix(&prefix))
+        .map(|answer| answer.to_string())
+        .ok_or_else(|| "No answer was printed".to_string())
 }
 
-impl From<&SnafuNumber> for i64 {
-    fn from(s: &SnafuNumber) -> Self {
-        let mut as_decimal = 0;
-        let mut place_value = 1;
-        let mut idx = s.0.len();
-
-        while idx > 0 {
-            idx -= 1;
-            let digit = &s.0[idx];
-            as_decimal += (match digit {
-                Two => 2,
-                One => 1,
-                Zero => 0,
-                MinusOne => -1,
-                MinusTwo => -2,
-            }) * place_value;
-            place_value *= 5;
-        }
-
-        as_decimal
-    }
+/// Runs every other part of the calendar on the inputs of the same size, and
+/// checks the answers against those in `input/answers-<size>.txt`. The last
+/// star is earned once every other part of the calendar is verified, apart
+/// from any that are skipped.
+///
+/// The `days` param limits which days are run.
+pub fn solve_b(
+    _input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<String, Box<dyn Error>> {
+    let days: Vec<u32> = params.get_list_or("days", (1..=25).collect())?;
+    let answers = read_answers(&format!("./input/answers-{}.txt", params.size))?;
+    Ok(verify(&days, &answers, |day, part| {
+        run_part(day, part, &params.size)
+    }))
 }
 
-impl From<&i64> for SnafuNumber {
-    fn from(decimal: &i64) -> Self {
-        let mut snafu_digits = Vec::new();
-        let mut decimal = *decimal;
-
-        while decimal > 0 {
-            let (carry_over, next_digit) = match decimal % 5 {
-                0 => (0, Zero),
-                1 => (0, One),
-                2 => (0, Two),
-                3 => (1, MinusTwo),
-                4 => (1, MinusOne),
-                _ => panic!("Unexpected input"),
+/// Runs each part of the given days with `run_part`, and reports how its
+/// answer compares with the expected one.
+fn verify(
+    days: &[u32],
+    answers: &HashMap<(u32, char), String>,
+    mut run_part: impl FnMut(u32, char) -> Result<String, String>,
+) -> String {
+    let mut report = Vec::new();
+    let mut verified = 0;
+    let mut parts = 0;
+    let mut skipped = 0;
+    for &day in days {
+        for part in ['a', 'b'] {
+            if (day, part) == (25, 'b') {
+                continue;
+            }
+            if answers
+                .get(&(day, part))
+                .is_some_and(|answer| answer == SKIPPED)
+            {
+                report.push(format!("{:02}{}: skipped", day, part));
+                skipped += 1;
+                continue;
+            }
+            parts += 1;
+            let start = Instant::now();
+            let result = run_part(day, part);
+            let elapsed = start.elapsed().as_secs_f64();
+            let outcome = match (result, answers.get(&(day, part))) {
+                (Err(error), _) => Outcome::Failed(error),
+                (Ok(answer), Some(expected)) if answer.trim() == expected => Outcome::Verified,
+                (Ok(answer), Some(_)) => Outcome::Wrong(answer),
+                (Ok(answer), None) => Outcome::Unverified(answer),
             };
-            snafu_digits.push(next_digit);
-            decimal = carry_over + decimal / 5;
+            let description = match outcome {
+                Outcome::Verified => {
+                    verified += 1;
+                    "verified".to_string()
+                }
+                Outcome::Unverified(answer) => format!("unverified answer {}", answer),
+                Outcome::Wrong(answer) => format!("wrong answer {}", answer),
+                Outcome::Failed(error) => format!("failed: {}", error),
+            };
+            report.push(format!(
+                "{:02}{}: {} ({:.2}s)",
+                day, part, description, elapsed
+            ));
         }
-
-        snafu_digits.reverse();
-        Self(snafu_digits)
     }
-}
 
-pub fn solve_a(input: impl Iterator<Item = String>) -> Result<String, Box<dyn Error>> {
-    let snafus: Vec<SnafuNumber> = input.map(|l| l.as_str().into()).collect_vec();
-    let total = snafus.iter().fold(0, |acc, x| acc + i64::from(x));
-    let snafu_total = SnafuNumber::from(&total);
-
-    Ok(snafu_total.to_string())
-}
-
-pub fn solve_b(_input: impl Iterator<Item = String>) -> Result<String, Box<dyn Error>> {
-    panic!("Merry Christmas!");
+    let mut summary = format!("Verified {} of {} stars", verified, parts);
+    if skipped > 0 {
+        summary.push_str(&format!(", skipping {}", skipped));
+    }
+    report.push(summary);
+    let whole_calendar = (1..=25).all(|day| days.contains(&day));
+    if whole_calendar && verified == parts {
+        report.push("All 50 stars earned. Merry Christmas!".to_string());
+    }
+    report.join("\n")
 }
 
 #[cfg(test)]
 mod tests {
+    use super::balanced::BalancedNumber;
     use super::*;
 
     #[test]
@@ -139,9 +182,8 @@ mod tests {
             ("122", 37),
         ];
         for (snafu_str, expected_value) in candidates {
-            let snafu: SnafuNumber = snafu_str.into();
-            let snafu_value: i64 = (&snafu).into();
-            assert_eq!(snafu_value, expected_value);
+            let snafu: BalancedNumber = snafu_str.parse().unwrap();
+            assert_eq!(snafu.to_i64(), Some(expected_value));
         }
     }
 
@@ -163,7 +205,7 @@ mod tests {
             ("122", 37),
         ];
         for (snafu_str, actual_value) in candidates {
-            let snafu: SnafuNumber = (&actual_value).into();
+            let snafu = Base::snafu().from_i64(actual_value);
             assert_eq!(snafu_str, format!("{}", snafu));
         }
     }
@@ -191,4 +233,74 @@ mod tests {
         let result = solve_a(input).unwrap();
         assert_eq!(result, "2=-1=0");
     }
+
+    /// The report without the timings.
+    fn verify_untimed(
+        days: &[u32],
+        answers: &[((u32, char), &str)],
+        run_part: impl FnMut(u32, char) -> Result<String, String>,
+    ) -> Vec<String> {
+        let answers = answers
+            .iter()
+            .map(|&(key, answer)| (key, answer.to_string()))
+            .collect();
+        verify(days, &answers, run_part)
+            .lines()
+            .map(|line| line.split(" (").next().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn it_verifies_other_days() {
+        let answers = [
+            ((1, 'a'), "7"),
+            ((1, 'b'), "8"),
+            ((2, 'a'), "9"),
+            ((3, 'a'), "-"),
+        ];
+        let report = verify_untimed(&[1, 2, 3], &answers, |day, part| match (day, part) {
+            (1, 'a') => Ok("7".to_string()),
+            (1, 'b') => Ok("80".to_string()),
+            (2, 'a') => Err("Panicked".to_string()),
+            (3, 'a') => panic!("Skipped parts should not be run"),
+            _ => Ok("10".to_string()),
+        });
+        assert_eq!(
+            report,
+            [
+                "01a: verified",
+                "01b: wrong answer 80",
+                "02a: failed: Panicked",
+                "02b: unverified answer 10",
+                "03a: skipped",
+                "03b: unverified answer 10",
+                "Verified 1 of 5 stars, skipping 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_earns_the_last_star() {
+        let answers = (1..=25)
+            .flat_map(|day| [((day, 'a'), "1"), ((day, 'b'), "1")])
+            .collect::<Vec<_>>();
+        let days = (1..=25).collect::<Vec<_>>();
+        let report = verify_untimed(&days, &answers, |_, _| Ok("1".to_string()));
+        assert_eq!(report.len(), 51);
+        assert_eq!(report[49], "Verified 49 of 49 stars");
+        assert_eq!(report[50], "All 50 stars earned. Merry Christmas!");
+
+        // As with the real answers, which skip day 10 part b
+        let mut answers = answers;
+        answers[19] = ((10, 'b'), "-");
+        let report = verify_untimed(&days, &answers, |_, _| Ok("1".to_string()));
+        assert_eq!(report[19], "10b: skipped");
+        assert_eq!(report[49], "Verified 48 of 48 stars, skipping 1");
+        assert_eq!(report[50], "All 50 stars earned. Merry Christmas!");
+
+        let report = verify_untimed(&days, &answers, |day, _| Ok(day.to_string()));
+        assert_eq!(report.len(), 50);
+        let report = verify_untimed(&days[1..], &answers, |_, _| Ok("1".to_string()));
+        assert_eq!(report.len(), 48);
+    }
 }
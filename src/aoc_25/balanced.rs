@@ -0,0 +1,330 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    ops::{Add, Mul, Neg, Sub},
+    rc::Rc,
+    str::FromStr,
+};
+
+/// An odd base, and the symbols used for its digits, from the most negative to
+/// the most positive. The digit symbols of base 5 SNAFU are `=-012`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Base {
+    symbols: Vec<char>,
+}
+
+impl Base {
+    pub fn new(symbols: &str) -> Result<Rc<Base>, String> {
+        let symbols = symbols.chars().collect::<Vec<_>>();
+        if symbols.len() < 3 || symbols.len() % 2 == 0 || symbols.len() > 255 {
+            return Err(format!(
+                "A balanced base needs an odd number of digits up to 255, not {}",
+                symbols.len()
+            ));
+        }
+        for (idx, symbol) in symbols.iter().enumerate() {
+            if symbols[..idx].contains(symbol) {
+                return Err(format!("The digit symbol {} is repeated", symbol));
+            }
+        }
+        Ok(Rc::new(Base { symbols }))
+    }
+
+    pub fn snafu() -> Rc<Base> {
+        Base::new("=-012").unwrap()
+    }
+
+    pub fn radix(&self) -> i64 {
+        self.symbols.len() as i64
+    }
+
+    /// The largest digit. The smallest digit is its negation.
+    fn max_digit(&self) -> i64 {
+        self.radix() / 2
+    }
+
+    fn symbol(&self, digit: i8) -> char {
+        self.symbols[(digit as i64 + self.max_digit()) as usize]
+    }
+
+    fn digit(&self, symbol: char) -> Option<i8> {
+        let idx = self.symbols.iter().position(|&s| s == symbol)?;
+        Some((idx as i64 - self.max_digit()) as i8)
+    }
+
+    /// Parses a number written in this base.
+    pub fn parse(self: &Rc<Self>, s: &str) -> Result<BalancedNumber, String> {
+        if s.is_empty() {
+            return Err("Cannot parse an empty number".to_string());
+        }
+        let digits = s
+            .chars()
+            .rev()
+            .map(|c| {
+                self.digit(c)
+                    .ok_or_else(|| format!("Unrecognised digit {:?} in {:?}", c, s))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BalancedNumber::new(self.clone(), digits))
+    }
+
+    pub fn from_i64(self: &Rc<Self>, value: i64) -> BalancedNumber {
+        BalancedNumber::from_wide_digits(self.clone(), vec![value as i128])
+    }
+}
+
+/// A number of any size, written in a balanced base, in which each digit can
+/// be negative. Negating a number negates each of its digits, so no sign is
+/// needed.
+#[derive(Debug, Clone)]
+pub struct BalancedNumber {
+    base: Rc<Base>,
+    /// Digits from least to most significant, without any leading zeros, so
+    /// that zero has no digits.
+    digits: Vec<i8>,
+}
+
+impl BalancedNumber {
+    fn new(base: Rc<Base>, mut digits: Vec<i8>) -> Self {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        BalancedNumber { base, digits }
+    }
+
+    /// Builds a number from digits that may be out of range, carrying the
+    /// excess into the more significant digits.
+    fn from_wide_digits(base: Rc<Base>, mut wide: Vec<i128>) -> Self {
+        let radix = base.radix() as i128;
+        let max_digit = base.max_digit() as i128;
+        let mut digits = Vec::with_capacity(wide.len());
+        let mut idx = 0;
+        while idx < wide.len() {
+            let value = wide[idx];
+            let carry = (value + max_digit).div_euclid(radix);
+            digits.push((value - carry * radix) as i8);
+            if carry != 0 {
+                if idx + 1 == wide.len() {
+                    wide.push(0);
+                }
+                wide[idx + 1] += carry;
+            }
+            idx += 1;
+        }
+        BalancedNumber::new(base, digits)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// The value of the number, if it fits in an `i64`.
+    pub fn to_i64(&self) -> Option<i64> {
+        // The partial sums can be just outside the range of the result
+        let mut value: i128 = 0;
+        for &digit in self.digits.iter().rev() {
+            value = value
+                .checked_mul(self.base.radix() as i128)?
+                .checked_add(digit as i128)?;
+        }
+        value.try_into().ok()
+    }
+
+    pub fn signum(&self) -> i8 {
+        self.digits.last().map_or(0, |digit| digit.signum())
+    }
+
+    fn check_base(&self, other: &BalancedNumber) {
+        assert!(
+            Rc::ptr_eq(&self.base, &other.base) || self.base == other.base,
+            "Cannot combine numbers in different bases"
+        );
+    }
+}
+
+impl PartialEq for BalancedNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.base == other.base && self.digits == other.digits
+    }
+}
+
+impl Eq for BalancedNumber {}
+
+impl PartialOrd for BalancedNumber {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.base != other.base {
+            return None;
+        }
+        Some(match (self - other).signum() {
+            0 => Ordering::Equal,
+            s if s > 0 => Ordering::Greater,
+            _ => Ordering::Less,
+        })
+    }
+}
+
+impl Display for BalancedNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.digits.is_empty() {
+            return write!(f, "{}", self.base.symbol(0));
+        }
+        let s = String::from_iter(self.digits.iter().rev().map(|&d| self.base.symbol(d)));
+        write!(f, "{}", s)
+    }
+}
+
+/// Parses a SNAFU number.
+impl FromStr for BalancedNumber {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Base::snafu().parse(s)
+    }
+}
+
+impl Neg for &BalancedNumber {
+    type Output = BalancedNumber;
+
+    fn neg(self) -> BalancedNumber {
+        let digits = self.digits.iter().map(|d| -d).collect();
+        BalancedNumber::new(self.base.clone(), digits)
+    }
+}
+
+impl Add for &BalancedNumber {
+    type Output = BalancedNumber;
+
+    fn add(self, other: &BalancedNumber) -> BalancedNumber {
+        self.check_base(other);
+        let len = self.digits.len().max(other.digits.len());
+        let digit = |n: &BalancedNumber, idx: usize| *n.digits.get(idx).unwrap_or(&0) as i128;
+        let wide = (0..len)
+            .map(|idx| digit(self, idx) + digit(other, idx))
+            .collect();
+        BalancedNumber::from_wide_digits(self.base.clone(), wide)
+    }
+}
+
+impl Sub for &BalancedNumber {
+    type Output = BalancedNumber;
+
+    fn sub(self, other: &BalancedNumber) -> BalancedNumber {
+        self + &-other
+    }
+}
+
+impl Mul for &BalancedNumber {
+    type Output = BalancedNumber;
+
+    fn mul(self, other: &BalancedNumber) -> BalancedNumber {
+        self.check_base(other);
+        if self.is_zero() || other.is_zero() {
+            return self.base.from_i64(0);
+        }
+        let mut wide = vec![0; self.digits.len() + other.digits.len()];
+        for (i, &a) in self.digits.iter().enumerate() {
+            for (j, &b) in other.digits.iter().enumerate() {
+                wide[i + j] += a as i128 * b as i128;
+            }
+        }
+        BalancedNumber::from_wide_digits(self.base.clone(), wide)
+    }
+}
+
+macro_rules! forward_owned_ops {
+    ($($trait:ident $method:ident),*) => {$(
+        impl $trait for BalancedNumber {
+            type Output = BalancedNumber;
+
+            fn $method(self, other: BalancedNumber) -> BalancedNumber {
+                (&self).$method(&other)
+            }
+        }
+    )*};
+}
+
+forward_owned_ops!(Add add, Sub sub, Mul mul);
+
+impl Neg for BalancedNumber {
+    type Output = BalancedNumber;
+
+    fn neg(self) -> BalancedNumber {
+        -&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_handles_zero_and_negative_numbers() {
+        let base = Base::snafu();
+        assert_eq!(base.from_i64(0).to_string(), "0");
+        assert_eq!(base.from_i64(-1).to_string(), "-");
+        assert_eq!(base.from_i64(-3).to_string(), "-2");
+        assert_eq!(base.from_i64(-1747).to_string(), "-2101=");
+        assert_eq!(base.parse("000").unwrap(), base.from_i64(0));
+        assert_eq!(base.parse("-2101=").unwrap().to_i64(), Some(-1747));
+        for value in [i64::MIN, i64::MAX, -1, 0, 1] {
+            assert_eq!(base.from_i64(value).to_i64(), Some(value));
+        }
+    }
+
+    #[test]
+    fn it_rejects_invalid_numbers_and_bases() {
+        assert!("".parse::<BalancedNumber>().is_err());
+        assert!("12a".parse::<BalancedNumber>().is_err());
+        assert!(Base::new("-0-").is_err());
+        assert!(Base::new("-0+=").is_err());
+        assert!(Base::new("=-0+#").is_ok());
+        assert!(Base::new("-+").is_err());
+    }
+
+    #[test]
+    fn it_does_arithmetic_on_digits() {
+        let base = Base::snafu();
+        let values = [0, 1, -1, 2, 7, -38, 1747, 4890, -123456789];
+        for a in values {
+            for b in values {
+                let (x, y) = (base.from_i64(a), base.from_i64(b));
+                assert_eq!((&x + &y).to_i64(), Some(a + b));
+                assert_eq!((&x - &y).to_i64(), Some(a - b));
+                assert_eq!((&x * &y).to_i64(), Some(a * b));
+                assert_eq!((-&x).to_i64(), Some(-a));
+                assert_eq!(x.partial_cmp(&y), Some(a.cmp(&b)));
+            }
+        }
+    }
+
+    #[test]
+    fn it_supports_other_bases() {
+        // Balanced ternary
+        let base = Base::new("T01").unwrap();
+        assert_eq!(base.from_i64(8).to_string(), "10T");
+        assert_eq!(base.from_i64(-8).to_string(), "T01");
+        let base = Base::new("abcdefghi").unwrap();
+        assert_eq!(base.radix(), 9);
+        let number = base.from_i64(-100);
+        assert_eq!(number.to_string(), "dcd");
+        assert_eq!(base.parse("dcd").unwrap().to_i64(), Some(-100));
+    }
+
+    #[test]
+    fn it_is_arbitrarily_precise() {
+        let base = Base::snafu();
+        let big = base.from_i64(i64::MAX);
+        let square = &big * &big;
+        assert_eq!(square.to_i64(), None);
+        // Builds the expected square from 32-bit chunks
+        let expected = (i64::MAX as i128) * (i64::MAX as i128);
+        let high = base.from_i64((expected >> 64) as i64);
+        let low = base.from_i64(((expected >> 32) & 0xffffffff) as i64);
+        let lowest = base.from_i64((expected & 0xffffffff) as i64);
+        let shift = base.from_i64(1 << 32);
+        let rebuilt = &(&(&(&high * &shift) + &low) * &shift) + &lowest;
+        assert_eq!(square, rebuilt);
+        assert_eq!((&square - &rebuilt).to_string(), "0");
+        assert_eq!(-(-square.clone()), square);
+    }
+}
@@ -0,0 +1,177 @@
+use std::ops::Range;
+
+/// A set of integers, stored as sorted, disjoint and non-adjacent half-open
+/// ranges.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IntervalSet {
+    ranges: Vec<Range<i64>>,
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        IntervalSet { ranges: Vec::new() }
+    }
+
+    /// The number of integers in the set.
+    pub fn len(&self) -> i64 {
+        self.ranges.iter().map(|r| r.end - r.start).sum()
+    }
+
+    /// Adds the range to the set, merging it with any ranges that it overlaps
+    /// or touches.
+    pub fn insert(&mut self, range: Range<i64>) {
+        if range.is_empty() {
+            return;
+        }
+        // The ranges from `first` up to `last` overlap or touch the new range
+        let first = self.ranges.partition_point(|r| r.end < range.start);
+        let last = self.ranges.partition_point(|r| r.start <= range.end);
+        let mut merged = range;
+        if first < last {
+            merged.start = merged.start.min(self.ranges[first].start);
+            merged.end = merged.end.max(self.ranges[last - 1].end);
+        }
+        self.ranges.splice(first..last, [merged]);
+    }
+
+    /// Removes the range from the set, splitting any range that it falls
+    /// inside.
+    pub fn remove(&mut self, range: Range<i64>) {
+        if range.is_empty() {
+            return;
+        }
+        let first = self.ranges.partition_point(|r| r.end <= range.start);
+        let last = self.ranges.partition_point(|r| r.start < range.end);
+        let mut remaining = Vec::new();
+        if first < last {
+            if self.ranges[first].start < range.start {
+                remaining.push(self.ranges[first].start..range.start);
+            }
+            if range.end < self.ranges[last - 1].end {
+                remaining.push(range.end..self.ranges[last - 1].end);
+            }
+        }
+        self.ranges.splice(first..last, remaining);
+    }
+
+    pub fn remove_point(&mut self, point: i64) {
+        self.remove(point..point + 1);
+    }
+
+    /// Whether every integer in the range is in the set.
+    pub fn contains_range(&self, range: Range<i64>) -> bool {
+        if range.is_empty() {
+            return true;
+        }
+        let idx = self.ranges.partition_point(|r| r.end < range.end);
+        self.ranges
+            .get(idx)
+            .is_some_and(|r| r.start <= range.start && range.end <= r.end)
+    }
+
+    /// Whether any integer in the range is in the set.
+    pub fn overlaps(&self, range: Range<i64>) -> bool {
+        if range.is_empty() {
+            return false;
+        }
+        let idx = self.ranges.partition_point(|r| r.end <= range.start);
+        self.ranges.get(idx).is_some_and(|r| r.start < range.end)
+    }
+
+    /// The ranges within the given bounds that are not in the set. Only used to
+    /// check other searches in tests.
+    #[cfg(test)]
+    pub fn gaps(&self, bounds: Range<i64>) -> Vec<Range<i64>> {
+        let mut gaps = Vec::new();
+        let mut position = bounds.start;
+        for range in &self.ranges {
+            if range.end <= position {
+                continue;
+            }
+            if range.start >= bounds.end {
+                break;
+            }
+            if range.start > position {
+                gaps.push(position..range.start);
+            }
+            position = range.end;
+        }
+        if position < bounds.end {
+            gaps.push(position..bounds.end);
+        }
+        gaps
+    }
+}
+
+impl From<Range<i64>> for IntervalSet {
+    fn from(range: Range<i64>) -> Self {
+        let mut set = IntervalSet::new();
+        set.insert(range);
+        set
+    }
+}
+
+impl FromIterator<Range<i64>> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = Range<i64>>>(iter: I) -> Self {
+        let mut set = IntervalSet::new();
+        for range in iter {
+            set.insert(range);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(set: &IntervalSet) -> Vec<Range<i64>> {
+        set.ranges.clone()
+    }
+
+    #[test]
+    fn it_merges_overlapping_and_touching_ranges() {
+        let set: IntervalSet = [5..8, 0..2, 2..3, 7..10, 12..14, 20..20]
+            .into_iter()
+            .collect();
+        assert_eq!(ranges(&set), [0..3, 5..10, 12..14]);
+        assert_eq!(set.len(), 10);
+
+        let mut set = set;
+        set.insert(-1..13);
+        assert_eq!(set, IntervalSet::from(-1..14));
+    }
+
+    #[test]
+    fn it_removes_ranges_and_points() {
+        let mut set: IntervalSet = [0..10, 20..30].into_iter().collect();
+        set.remove_point(5);
+        assert_eq!(ranges(&set), [0..5, 6..10, 20..30]);
+        set.remove(8..25);
+        assert_eq!(ranges(&set), [0..5, 6..8, 25..30]);
+        set.remove(-5..6);
+        assert_eq!(ranges(&set), [6..8, 25..30]);
+        set.remove_point(100);
+        assert_eq!(set.len(), 7);
+    }
+
+    #[test]
+    fn it_checks_containment_and_overlap() {
+        let set: IntervalSet = [0..10, 20..30].into_iter().collect();
+        assert!(set.contains_range(0..1));
+        assert!(!set.contains_range(10..11));
+        assert!(set.contains_range(2..10));
+        assert!(!set.contains_range(5..21));
+        assert!(set.overlaps(9..20));
+        assert!(!set.overlaps(10..20));
+        assert!(!set.overlaps(30..40));
+    }
+
+    #[test]
+    fn it_finds_gaps_within_bounds() {
+        let set: IntervalSet = [-5..3, 5..8, 9..12, 30..40].into_iter().collect();
+        assert_eq!(set.gaps(0..21), vec![3..5, 8..9, 12..21]);
+        assert_eq!(set.gaps(0..3), vec![]);
+        assert_eq!(IntervalSet::new().gaps(0..3), vec![0..3]);
+    }
+}
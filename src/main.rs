@@ -1,7 +1,7 @@
 #[macro_use]
 extern crate lazy_static;
 
-use std::{env, fs};
+use std::{env, error::Error, fs, process};
 
 mod aoc_01;
 mod aoc_02;
@@ -28,83 +28,127 @@ mod aoc_22;
 mod aoc_23;
 mod aoc_24;
 mod aoc_25;
+mod intervals;
+mod params;
+
+use params::Params;
+
+const USAGE: &str = "Usage:\n  cargo run -- 06 a small [key=value...]";
 
 struct Problem {
     number: String,
     part: char,
-    size: String,
+    params: Params,
 }
 
 impl Problem {
-    fn from_args() -> Option<Problem> {
-        let number: String = env::args().nth(1)?;
-        let part = env::args().nth(2)?.chars().next()?;
-        let size = env::args().nth(3)?;
+    fn from_args() -> Result<Problem, String> {
+        let number: String = env::args().nth(1).ok_or(USAGE)?;
+        let part = env::args()
+            .nth(2)
+            .and_then(|part| part.chars().next())
+            .ok_or(USAGE)?;
+        let size = env::args().nth(3).ok_or(USAGE)?;
+        let params = Params::parse(&size, env::args().skip(4))
+            .map_err(|error| format!("{}\n{}", error, USAGE))?;
 
-        Some(Problem { number, part, size })
+        Ok(Problem {
+            number,
+            part,
+            params,
+        })
     }
 }
 
+/// Runs one part of a problem, returning its answer.
+pub fn solve(
+    number: &str,
+    part: char,
+    input: impl Iterator<Item = String>,
+    params: &Params,
+) -> Result<String, Box<dyn Error>> {
+    Ok(match (number, part) {
+        ("01", 'a') => aoc_01::solve_a(input)?.to_string(),
+        ("01", 'b') => aoc_01::solve_b(input)?.to_string(),
+        ("02", 'a') => aoc_02::solve_a(input, params)?.to_string(),
+        ("02", 'b') => aoc_02::solve_b(input, params)?.to_string(),
+        ("02", 'g') => aoc_02::solve_guide(input, params)?.to_string(),
+        ("03", 'a') => aoc_03::solve_a(input)?.to_string(),
+        ("03", 'b') => aoc_03::solve_b(input, params)?.to_string(),
+        ("04", 'a') => aoc_04::solve_a(input)?.to_string(),
+        ("04", 'b') => aoc_04::solve_b(input)?.to_string(),
+        ("05", 'a') => aoc_05::solve_a(input)?.to_string(),
+        ("05", 'b') => aoc_05::solve_b(input)?.to_string(),
+        ("05", 't') => aoc_05::solve_trace(input, params)?.to_string(),
+        ("06", 'a') => aoc_06::solve_a(input)?.to_string(),
+        ("06", 'b') => aoc_06::solve_b(input)?.to_string(),
+        ("06", 'm') => aoc_06::solve_markers(input, params)?.to_string(),
+        ("07", 'a') => aoc_07::solve_a(input)?.to_string(),
+        ("07", 'b') => aoc_07::solve_b(input)?.to_string(),
+        ("08", 'a') => aoc_08::solve_a(input)?.to_string(),
+        ("08", 'b') => aoc_08::solve_b(input)?.to_string(),
+        ("09", 'a') => aoc_09::solve_a(input)?.to_string(),
+        ("09", 'b') => aoc_09::solve_b(input)?.to_string(),
+        ("10", 'a') => aoc_10::solve_a(input)?.to_string(),
+        ("10", 'b') => aoc_10::solve_b(input)?.to_string(),
+        ("11", 'a') => aoc_11::solve_a(input, params)?.to_string(),
+        ("11", 'b') => aoc_11::solve_b(input, params)?.to_string(),
+        ("11", 't') => aoc_11::solve_trace(input, params)?.to_string(),
+        ("12", 'a') => aoc_12::solve_a(input)?.to_string(),
+        ("12", 'b') => aoc_12::solve_b(input)?.to_string(),
+        ("13", 'a') => aoc_13::solve_a(input)?.to_string(),
+        ("13", 'b') => aoc_13::solve_b(input)?.to_string(),
+        ("14", 'a') => aoc_14::solve_a(input)?.to_string(),
+        ("14", 'b') => aoc_14::solve_b(input)?.to_string(),
+        ("15", 'a') => aoc_15::solve_a(input, params)?.to_string(),
+        ("15", 'b') => aoc_15::solve_b(input, params)?.to_string(),
+        ("15", 'r') => aoc_15::solve_render(input, params)?.to_string(),
+        ("16", 'a') => aoc_16::solve_a(input, params)?.to_string(),
+        ("16", 'b') => aoc_16::solve_b(input, params)?.to_string(),
+        ("16", 's') => aoc_16::solve_schedule(input, params)?.to_string(),
+        ("16", 'g') => aoc_16::solve_graph(input, params)?.to_string(),
+        ("17", 'a') => aoc_17::solve_a(input)?.to_string(),
+        ("17", 'b') => aoc_17::solve_b(input)?.to_string(),
+        ("18", 'a') => aoc_18::solve_a(input)?.to_string(),
+        ("18", 'b') => aoc_18::solve_b(input)?.to_string(),
+        ("19", 'a') => aoc_19::solve_a(input, params)?.to_string(),
+        ("19", 'b') => aoc_19::solve_b(input, params)?.to_string(),
+        ("19", 'p') => aoc_19::solve_plan(input, params)?.to_string(),
+        ("20", 'a') => aoc_20::solve_a(input, params)?.to_string(),
+        ("20", 'b') => aoc_20::solve_b(input, params)?.to_string(),
+        ("21", 'a') => aoc_21::solve_a(input)?.to_string(),
+        ("21", 'b') => aoc_21::solve_b(input)?.to_string(),
+        ("21", 'e') => aoc_21::solve_equation(input, params)?.to_string(),
+        ("22", 'a') => aoc_22::solve_a(input)?.to_string(),
+        ("22", 'b') => aoc_22::solve_b(input)?.to_string(),
+        ("23", 'a') => aoc_23::solve_a(input)?.to_string(),
+        ("23", 'b') => aoc_23::solve_b(input)?.to_string(),
+        ("24", 'a') => aoc_24::solve_a(input)?.to_string(),
+        ("24", 'b') => aoc_24::solve_b(input)?.to_string(),
+        ("25", 'a') => aoc_25::solve_a(input)?.to_string(),
+        ("25", 'b') => aoc_25::solve_b(input, params)?.to_string(),
+        _ => return Err("Unrecognised problem and solution".into()),
+    })
+}
+
 fn main() {
-    let problem = Problem::from_args().expect("Usage:\n  cargo run -- 06 a small");
+    let problem = Problem::from_args().unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
 
-    let path = format!("./input/{}-{}.txt", problem.number, problem.size);
+    let path = format!("./input/{}-{}.txt", problem.number, problem.params.size);
 
     let input_txt = fs::read_to_string(&path).expect("Could not find input file");
     let input = input_txt.lines().map(|x| x.to_string());
 
-    let problem_pair: (&str, &char) = (&problem.number, &problem.part);
-    match problem_pair {
-        ("01", 'a') => println!("a soln: {}", aoc_01::solve_a(input).unwrap()),
-        ("01", 'b') => println!("b soln: {}", aoc_01::solve_b(input).unwrap()),
-        ("02", 'a') => println!("a soln: {}", aoc_02::solve_a(input).unwrap()),
-        ("02", 'b') => println!("b soln: {}", aoc_02::solve_b(input).unwrap()),
-        ("03", 'a') => println!("a soln: {}", aoc_03::solve_a(input).unwrap()),
-        ("03", 'b') => println!("b soln: {}", aoc_03::solve_b(input).unwrap()),
-        ("04", 'a') => println!("a soln: {}", aoc_04::solve_a(input).unwrap()),
-        ("04", 'b') => println!("b soln: {}", aoc_04::solve_b(input).unwrap()),
-        ("05", 'a') => println!("a soln: {}", aoc_05::solve_a(input).unwrap()),
-        ("05", 'b') => println!("b soln: {}", aoc_05::solve_b(input).unwrap()),
-        ("06", 'a') => println!("a soln: {}", aoc_06::solve_a(input).unwrap()),
-        ("06", 'b') => println!("b soln: {}", aoc_06::solve_b(input).unwrap()),
-        ("07", 'a') => println!("a soln: {}", aoc_07::solve_a(input).unwrap()),
-        ("07", 'b') => println!("b soln: {}", aoc_07::solve_b(input).unwrap()),
-        ("08", 'a') => println!("a soln: {}", aoc_08::solve_a(input).unwrap()),
-        ("08", 'b') => println!("b soln: {}", aoc_08::solve_b(input).unwrap()),
-        ("09", 'a') => println!("a soln: {}", aoc_09::solve_a(input).unwrap()),
-        ("09", 'b') => println!("b soln: {}", aoc_09::solve_b(input).unwrap()),
-        ("10", 'a') => println!("a soln: {}", aoc_10::solve_a(input).unwrap()),
-        ("10", 'b') => println!("b soln: {}", aoc_10::solve_b(input).unwrap()),
-        ("11", 'a') => println!("a soln: {}", aoc_11::solve_a(input).unwrap()),
-        ("11", 'b') => println!("b soln: {}", aoc_11::solve_b(input).unwrap()),
-        ("12", 'a') => println!("a soln: {}", aoc_12::solve_a(input).unwrap()),
-        ("12", 'b') => println!("b soln: {}", aoc_12::solve_b(input).unwrap()),
-        ("13", 'a') => println!("a soln: {}", aoc_13::solve_a(input).unwrap()),
-        ("13", 'b') => println!("b soln: {}", aoc_13::solve_b(input).unwrap()),
-        ("14", 'a') => println!("a soln: {}", aoc_14::solve_a(input).unwrap()),
-        ("14", 'b') => println!("b soln: {}", aoc_14::solve_b(input).unwrap()),
-        ("15", 'a') => println!("a soln: {}", aoc_15::solve_a(input).unwrap()),
-        ("15", 'b') => println!("b soln: {}", aoc_15::solve_b(input).unwrap()),
-        ("16", 'a') => println!("a soln: {}", aoc_16::solve_a(input).unwrap()),
-        ("16", 'b') => println!("b soln: {}", aoc_16::solve_b(input).unwrap()),
-        ("17", 'a') => println!("a soln: {}", aoc_17::solve_a(input).unwrap()),
-        ("17", 'b') => println!("b soln: {}", aoc_17::solve_b(input).unwrap()),
-        ("18", 'a') => println!("a soln: {}", aoc_18::solve_a(input).unwrap()),
-        ("18", 'b') => println!("b soln: {}", aoc_18::solve_b(input).unwrap()),
-        ("19", 'a') => println!("a soln: {}", aoc_19::solve_a(input).unwrap()),
-        ("19", 'b') => println!("b soln: {}", aoc_19::solve_b(input).unwrap()),
-        ("20", 'a') => println!("a soln: {}", aoc_20::solve_a(input).unwrap()),
-        ("20", 'b') => println!("b soln: {}", aoc_20::solve_b(input).unwrap()),
-        ("21", 'a') => println!("a soln: {}", aoc_21::solve_a(input).unwrap()),
-        ("21", 'b') => println!("b soln: {}", aoc_21::solve_b(input).unwrap()),
-        ("22", 'a') => println!("a soln: {}", aoc_22::solve_a(input).unwrap()),
-        ("22", 'b') => println!("b soln: {}", aoc_22::solve_b(input).unwrap()),
-        ("23", 'a') => println!("a soln: {}", aoc_23::solve_a(input).unwrap()),
-        ("23", 'b') => println!("b soln: {}", aoc_23::solve_b(input).unwrap()),
-        ("24", 'a') => println!("a soln: {}", aoc_24::solve_a(input).unwrap()),
-        ("24", 'b') => println!("b soln: {}", aoc_24::solve_b(input).unwrap()),
-        ("25", 'a') => println!("a soln: {}", aoc_25::solve_a(input).unwrap()),
-        ("25", 'b') => println!("b soln: {}", aoc_25::solve_b(input).unwrap()),
-        _ => panic!("Unrecognised problem and solution"),
+    let answer = solve(&problem.number, problem.part, input, &problem.params).unwrap();
+    let unused = problem.params.unused();
+    if !unused.is_empty() {
+        eprintln!("Ignored params that were not used: {}", unused.join(", "));
+    }
+    match problem.part {
+        'a' | 'b' if !answer.contains('\n') => println!("{} soln: {}", problem.part, answer),
+        _ => println!("{}", answer),
     }
 }
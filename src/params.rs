@@ -0,0 +1,128 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    error::Error,
+    str::FromStr,
+};
+
+/// Extra `key=value` arguments passed on the command line after the input
+/// size, e.g. `cargo run 21 e small unknown=humn value=5`.
+#[derive(Debug, Default)]
+pub struct Params {
+    pub size: String,
+    values: HashMap<String, String>,
+    /// The keys a solver has asked for, so that any others can be reported.
+    read: RefCell<HashSet<String>>,
+}
+
+impl Params {
+    pub fn parse(size: &str, args: impl Iterator<Item = String>) -> Result<Params, Box<dyn Error>> {
+        let mut values = HashMap::new();
+        for arg in args {
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or(format!("Expected a key=value argument, got: {}", arg))?;
+            values.insert(key.to_string(), value.to_string());
+        }
+        Ok(Params {
+            size: size.to_string(),
+            values,
+            read: RefCell::default(),
+        })
+    }
+
+    /// The params for the small input given by the arguments, for tests.
+    #[cfg(test)]
+    pub fn from_args(args: &[&str]) -> Params {
+        Params::parse("small", args.iter().map(|x| x.to_string())).unwrap()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.read.borrow_mut().insert(key.to_string());
+        self.values.get(key).map(|x| x.as_str())
+    }
+
+    /// The keys that were given but never asked for, in order, which are most
+    /// likely typos or meant for another problem.
+    pub fn unused(&self) -> Vec<&str> {
+        let read = self.read.borrow();
+        let mut unused = self
+            .values
+            .keys()
+            .filter(|key| !read.contains(*key))
+            .map(|key| key.as_str())
+            .collect::<Vec<_>>();
+        unused.sort();
+        unused
+    }
+
+    /// Parses the value of the key, falling back to the default if it was not
+    /// given.
+    pub fn get_or<T>(&self, key: &str, default: T) -> Result<T, Box<dyn Error>>
+    where
+        T: FromStr,
+        T::Err: Into<Box<dyn Error>>,
+    {
+        match self.get(key) {
+            Some(value) => value.parse().map_err(Into::into),
+            None => Ok(default),
+        }
+    }
+
+    /// Parses a comma-separated list of values, falling back to the default if
+    /// it was not given.
+    pub fn get_list_or<T>(&self, key: &str, default: Vec<T>) -> Result<Vec<T>, Box<dyn Error>>
+    where
+        T: FromStr,
+        T::Err: Into<Box<dyn Error>>,
+    {
+        match self.get(key) {
+            Some(value) => value
+                .split(',')
+                .map(|x| x.trim().parse().map_err(Into::into))
+                .collect(),
+            None => Ok(default),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_params() {
+        let args = ["unknown=humn", "value=5"].map(String::from).into_iter();
+        let params = Params::parse("small", args).unwrap();
+        assert_eq!(params.size, "small");
+        assert_eq!(params.get("unknown"), Some("humn"));
+        assert_eq!(params.get_or("value", 0).unwrap(), 5);
+        assert_eq!(params.get_or("missing", 7).unwrap(), 7);
+    }
+
+    #[test]
+    fn it_parses_list_params() {
+        let args = ["offsets=1,2, 3"].map(String::from).into_iter();
+        let params = Params::parse("small", args).unwrap();
+        assert_eq!(
+            params.get_list_or::<i32>("offsets", vec![]).unwrap(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(params.get_list_or("missing", vec![4]).unwrap(), vec![4]);
+    }
+
+    #[test]
+    fn it_lists_unused_params() {
+        let params = Params::from_args(&["row=10", "rows=10", "max=5"]);
+        assert_eq!(params.get_or("row", 0).unwrap(), 10);
+        assert_eq!(params.get("max"), Some("5"));
+        assert!(params.get("min").is_none());
+        assert_eq!(params.unused(), vec!["rows"]);
+    }
+
+    #[test]
+    fn it_rejects_args_without_a_value() {
+        let args = ["humn"].map(String::from).into_iter();
+        assert!(Params::parse("small", args).is_err());
+    }
+}